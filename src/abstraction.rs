@@ -1,21 +1,51 @@
 // abstraction.rs
 
+use crate::breaker::ProxyBreaker;
+use crate::client::ConnStats;
 use crate::error::{
-    missing_param, net_work_error, other, other_without_source, parse_error, Result,
+    banned, decode_error, invalid_input, missing_param, net_work_error, other_without_source,
+    parse_error, rate_limited, rejected, unsupported, upstream_status, Error, Result,
 };
-use reqwest::blocking::Client;
+use crate::timing::{Stopwatch, Timings};
+use crate::traffic::Traffic;
+use crate::w::WDebug;
+use reqwest::blocking::{Client, RequestBuilder};
+use schemars::JsonSchema;
+use serde::Deserialize;
 use serde_json::Value;
 use std::collections::HashMap;
+use std::io::Read;
 // 修改：引入 SystemTime 和 UNIX_EPOCH 用于生成时间戳
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-#[derive(Clone, Copy)]
-pub(crate) enum VerifyType {
+/// ### 验证码类型
+/// - Nine（九宫格图标）与 Beeline（一笔画）目前只能识别，求解接口会返回 Unsupported
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum VerifyType {
     Slide,
     Click,
+    Nine,
+    Beeline,
 }
 
-pub(crate) trait Api {
+impl VerifyType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            VerifyType::Slide => "slide",
+            VerifyType::Click => "click",
+            VerifyType::Nine => "nine",
+            VerifyType::Beeline => "beeline",
+        }
+    }
+
+    /// 尚未实现求解的类型走到求解流程时返回的错误
+    pub fn unsupported(self) -> Error {
+        unsupported(&format!("暂不支持求解 {} 类型验证码", self.as_str()))
+    }
+}
+
+pub trait Api {
     type ArgsType;
 
     /// ### 申请验证码
@@ -23,7 +53,9 @@ pub(crate) trait Api {
     /// - gt
     /// - challenge
     fn register_test(&self, url: &str) -> Result<(String, String)> {
-        let res = self.client().get(url).send().map_err(net_work_error)?;
+        self.record_request();
+        let builder = with_referer(with_cookies(self.client().get(url), self.target_cookies()), self.referer());
+        let res = builder.send().map_err(net_work_error)?;
         // 改进：使用安全的错误处理替换 expect
         let res = res.json::<Value>().map_err(parse_error)?;
         Ok((
@@ -45,15 +77,19 @@ pub(crate) trait Api {
     /// - c
     /// - s
     fn get_c_s(&self, gt: &str, challenge: &str, w: Option<&str>) -> Result<(Vec<u8>, String)> {
+        self.get_c_s_timed(gt, challenge, w).map(|(c, s, _)| (c, s))
+    }
+
+    /// ### 获取c和s参数并统计耗时
+    /// #### 返回值
+    /// - c
+    /// - s
+    /// - 网络与本地计算的耗时
+    fn get_c_s_timed(&self, gt: &str, challenge: &str, w: Option<&str>) -> Result<(Vec<u8>, String, Timings)> {
+        let mut watch = Stopwatch::start();
         // 修改：生成动态回调
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("Time went backwards")
-            .as_millis()
-            .to_string();
-        let callback = format!("geetest_{}", timestamp);
-
-        let url = "http://api.geetest.com/get.php";
+        let callback = jsonp_callback();
+
         let mut params = HashMap::from([
             ("gt", gt),
             ("challenge", challenge),
@@ -62,49 +98,29 @@ pub(crate) trait Api {
         if let Some(w) = w {
             params.insert("w", w);
         }
-        let res = self
-            .client()
-            .get(url)
-            .query(&params)
-            .send()
-            .map_err(net_work_error)?;
-        let res = res.text().map_err(|e| other("什么b玩意错误", e))?;
-
-        // 修改：使用动态回调作为前缀
-        let prefix = format!("{}(", callback);
-        let res = res
-            .strip_prefix(&prefix)
-            .ok_or_else(|| other_without_source("前缀错误"))?
-            .strip_suffix(")")
-            .ok_or_else(|| other_without_source("后缀错误"))?;
-        let res: Value = serde_json::from_str(res).map_err(parse_error)?;
-        let data = res.get("data").ok_or_else(|| missing_param("data"))?;
-        let c: Vec<u8> =
-            serde_json::from_value(data.get("c").ok_or_else(|| missing_param("c"))?.clone())
-                .map_err(parse_error)?;
-        Ok((
-            c,
-            data.get("s")
-                .ok_or_else(|| missing_param("s"))?
-                .as_str()
-                .ok_or_else(|| missing_param("s"))?
-                .to_string(),
-        ))
+        let builder = self.geetest_get("get.php").query(&params);
+        self.record_request();
+        let res = watch.network(|| send_text(builder, self.traffic()))?;
+
+        let (c, s) = parse_c_s(&parse_jsonp(&res, &callback)?)?;
+        Ok((c, s, watch.finish()))
     }
 
     /// ### 获取验证码类型
     /// #### 返回值
     /// - 验证码类型
     fn get_type(&self, gt: &str, challenge: &str, w: Option<&str>) -> Result<VerifyType> {
+        self.get_type_raw(gt, challenge, w).map(|(t, _)| t)
+    }
+
+    /// ### 获取验证码类型及极验原始响应
+    /// #### 返回值
+    /// - 验证码类型
+    /// - 极验返回的原始响应（含 jsonp 包裹）
+    fn get_type_raw(&self, gt: &str, challenge: &str, w: Option<&str>) -> Result<(VerifyType, String)> {
         // 修改：生成动态回调
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("Time went backwards")
-            .as_millis()
-            .to_string();
-        let callback = format!("geetest_{}", timestamp);
-
-        let url = "http://api.geetest.com/ajax.php";
+        let callback = jsonp_callback();
+
         let mut params = HashMap::from([
             ("gt", gt),
             ("challenge", challenge),
@@ -113,33 +129,24 @@ pub(crate) trait Api {
         if let Some(w) = w {
             params.insert("w", w);
         }
-        let res = self
-            .client()
-            .get(url)
-            .query(&params)
-            .send()
-            .map_err(net_work_error)?;
-        let res = res.text().map_err(|e| other("什么b玩意错误", e))?;
-
-        // 修改：使用动态回调作为前缀
-        let prefix = format!("{}(", callback);
-        let res = res
-            .strip_prefix(&prefix)
-            .ok_or_else(|| other_without_source("前缀错误"))?
-            .strip_suffix(")")
-            .ok_or_else(|| other_without_source("后缀错误"))?;
-        let res: Value = serde_json::from_str(res).map_err(parse_error)?;
+        self.record_request();
+        let raw = send_text(self.geetest_get("ajax.php").query(&params), self.traffic())?;
+
+        let res = parse_jsonp(&raw, &callback)?;
         let data = res.get("data").ok_or_else(|| missing_param("data"))?;
         let result = data
             .get("result")
             .ok_or_else(|| missing_param("result"))?
             .as_str()
             .ok_or_else(|| missing_param("result"))?;
-        match result {
-            "slide" => Ok(VerifyType::Slide),
-            "click" => Ok(VerifyType::Click),
-            _ => Err(other_without_source("未知验证码类型")),
-        }
+        let verify_type = match result {
+            "slide" => VerifyType::Slide,
+            "click" => VerifyType::Click,
+            "nine" => VerifyType::Nine,
+            "beeline" => VerifyType::Beeline,
+            _ => return Err(other_without_source("未知验证码类型")),
+        };
+        Ok((verify_type, raw))
     }
 
     /// ### 获取新的c,s,challenge参数和验证所需要的参数
@@ -157,26 +164,79 @@ pub(crate) trait Api {
     /// #### 返回值
     /// - message
     /// - validate
-    fn verify(&self, gt: &str, challenge: &str, w: Option<&str>) -> Result<(String, String)>;
+    fn verify(&self, gt: &str, challenge: &str, w: Option<&str>) -> Result<(String, String)> {
+        self.verify_full(gt, challenge, w).map(|(message, validate, _)| (message, validate))
+    }
+
+    /// ### 验证并保留极验的完整响应
+    /// #### 返回值
+    /// - message
+    /// - validate
+    /// - 完整验证结果，见 `VerifyPayload`
+    fn verify_full(
+        &self,
+        gt: &str,
+        challenge: &str,
+        w: Option<&str>,
+    ) -> Result<(String, String, VerifyPayload)> {
+        self.verify_full_timed(gt, challenge, w)
+            .map(|(message, validate, payload, _)| (message, validate, payload))
+    }
+
+    /// ### 验证并统计耗时
+    /// #### 返回值
+    /// - message
+    /// - validate
+    /// - 完整验证结果，见 `VerifyPayload`
+    /// - 网络与本地计算的耗时
+    fn verify_full_timed(
+        &self,
+        gt: &str,
+        challenge: &str,
+        w: Option<&str>,
+    ) -> Result<(String, String, VerifyPayload, Timings)>;
 
     /// ### 刷新
     /// #### 返回值
     /// - args: 计算key用到的参数
     fn refresh(&self, gt: &str, challenge: &str) -> Result<Self::ArgsType>;
 
+    /// ### 刷新验证码并取得新的 challenge
+    /// - 调用极验 refresh.php，challenge 可能位于 data 下或顶层
+    /// - 极验未下发新 challenge 时沿用原值（只更换了图片）
+    /// #### 返回值
+    /// - 刷新后的 challenge
+    fn refresh_challenge(&self, gt: &str, challenge: &str) -> Result<String> {
+        let callback = jsonp_callback();
+
+        let params = HashMap::from([
+            ("gt", gt),
+            ("challenge", challenge),
+            ("callback", callback.as_str()),
+        ]);
+        let builder = self.geetest_get("refresh.php").query(&params);
+        self.record_request();
+        let res = send_text(builder, self.traffic())?;
+
+        let res = parse_jsonp(&res, &callback)?;
+        let new_challenge = res
+            .get("data")
+            .and_then(|data| data.get("challenge"))
+            .or_else(|| res.get("challenge"))
+            .and_then(Value::as_str)
+            .unwrap_or(challenge);
+        Ok(new_challenge.to_string())
+    }
+
     /// ### 下载图片
     /// #### 返回值
     /// - img
     fn download_img(&self, img_url: &str) -> Result<Vec<u8>> {
-        // 使用不带代理的客户端
-        let res = self
-            .noproxy_client()
-            .get(img_url)
-            .send()
-            .map_err(net_work_error)?;
-        // 改进：使用安全的错误处理替换 unwrap
-        let bytes = res.bytes().map_err(net_work_error)?;
-        Ok(bytes.to_vec())
+        // 使用不带代理的客户端；开启 GT_REQUIRE_PROXY 时两者是同一个代理客户端
+        if std::ptr::eq(self.client(), self.noproxy_client()) {
+            self.record_request();
+        }
+        send_bytes(self.noproxy_client().get(img_url), self.traffic(), self.max_image_bytes())
     }
 
     /// 返回可能带代理的客户端
@@ -184,9 +244,57 @@ pub(crate) trait Api {
 
     /// 返回一个永不带代理的客户端，用于下载图片
     fn noproxy_client(&self) -> &Client;
+
+    /// 图片下载大小上限（字节），超出时放弃下载并返回解析错误
+    fn max_image_bytes(&self) -> u64 {
+        DEFAULT_MAX_IMAGE_BYTES
+    }
+
+    /// 当前代理的连接统计，不带代理时为 None
+    fn conn_stats(&self) -> Option<&ConnStats> {
+        None
+    }
+
+    /// 当前代理的熔断句柄，不带代理或关闭熔断时为 None
+    fn breaker(&self) -> Option<&ProxyBreaker> {
+        None
+    }
+
+    /// 发出一次请求前调用，供连接复用统计估算复用次数
+    fn record_request(&self) {
+        if let Some(stats) = self.conn_stats() {
+            stats.record_request();
+        }
+    }
+
+    /// 调用方传入的 Cookie 请求头，附加到所有极验接口请求上，见 `geetest_get`
+    fn cookies(&self) -> Option<&str>;
+
+    /// 目标站点的 Cookie 请求头，只附加到 register_test 请求上，见 `with_cookies`
+    fn target_cookies(&self) -> Option<&str>;
+
+    /// 调用方传入的 Referer 请求头，附加到 register_test 与所有极验接口请求上
+    fn referer(&self) -> Option<&str>;
+
+    /// 极验接口地址，默认 `DEFAULT_BASE_URL`
+    fn base_url(&self) -> &str;
+
+    /// 设置时录制或回放极验请求，见 `Traffic`
+    fn traffic(&self) -> Option<&Traffic>;
+
+    /// 拼接极验接口地址，例如 `endpoint("get.php")`
+    fn endpoint(&self, path: &str) -> String {
+        endpoint(self.base_url(), path)
+    }
+
+    /// ### 发往极验接口的 GET 请求
+    /// - get.php、ajax.php、refresh.php 都经此构建，统一附加调用方传入的 Cookie 与 Referer
+    fn geetest_get(&self, path: &str) -> RequestBuilder {
+        with_referer(with_cookies(self.client().get(self.endpoint(path)), self.cookies()), self.referer())
+    }
 }
 
-pub(crate) trait GenerateW: Api {
+pub trait GenerateW: Api {
     /// ### 计算关键参数
     /// - 不同验证类型的关键参数不同
     fn calculate_key(&mut self, args: Self::ArgsType) -> Result<String>;
@@ -199,9 +307,511 @@ pub(crate) trait GenerateW: Api {
         c: &[u8],
         s: &str,
     ) -> Result<String>;
+    /// ### 根据关键参数生成w，同时返回中间值
+    /// - 与 generate_w 分开，避免正常路径收集调试信息
+    fn generate_w_debug(
+        &self,
+        key: &str,
+        gt: &str,
+        challenge: &str,
+        c: &[u8],
+        s: &str,
+    ) -> Result<(String, WDebug)>;
 }
 
-pub(crate) trait Test: Api + GenerateW {
+pub trait Test: Api + GenerateW {
     /// ### 测试
     fn test(&mut self, url: &str) -> Result<String>;
-}
\ No newline at end of file
+}
+
+/// ### 求解过程中下载的验证码图片
+/// - 调用 `set_capture_images(true)` 后才会保留，用于收集训练数据
+#[derive(Clone, Debug)]
+pub struct CapturedImage {
+    pub url: String,
+    pub bytes: Vec<u8>,
+}
+
+/// 默认的极验接口地址，可通过 GEETEST_BASE_URL 覆盖，用于私有部署或测试桩
+pub const DEFAULT_BASE_URL: &str = "http://api.geetest.com";
+
+/// 默认的图片下载大小上限: 4 MiB，极验的验证码图片通常只有几十 KB
+pub const DEFAULT_MAX_IMAGE_BYTES: u64 = 4 * 1024 * 1024;
+
+pub(crate) fn endpoint(base_url: &str, path: &str) -> String {
+    format!("{}/{}", base_url.trim_end_matches('/'), path)
+}
+
+/// ### 完整的验证结果
+/// - 极验验证响应中的全部字段（点选取 data 下的字段）
+/// - 外加站点后端二次校验所需的 geetest_challenge、geetest_validate、geetest_seccode
+pub type VerifyPayload = serde_json::Map<String, Value>;
+
+/// ### 由验证响应构造完整验证结果
+/// - 响应带新的 challenge 时以响应为准
+/// - seccode 按极验前端的规则拼接为 `<validate>|jordan`
+pub(crate) fn verify_payload(fields: &Value, challenge: &str, validate: &str) -> VerifyPayload {
+    let mut payload = fields.as_object().cloned().unwrap_or_default();
+    let challenge = fields
+        .get("challenge")
+        .and_then(Value::as_str)
+        .unwrap_or(challenge)
+        .to_string();
+    payload.insert("geetest_challenge".to_string(), Value::String(challenge));
+    payload.insert("geetest_validate".to_string(), Value::String(validate.to_string()));
+    payload.insert("geetest_seccode".to_string(), Value::String(format!("{}|jordan", validate)));
+    payload
+}
+
+/// gt 与初始 challenge 的长度
+const GT_LEN: usize = 32;
+/// 滑块取图后 challenge 会追加 2 位
+const REFRESHED_CHALLENGE_LEN: usize = GT_LEN + 2;
+
+/// ### 校验 gt 与 challenge 的格式
+/// - gt: 32 位十六进制
+/// - challenge: 32 位十六进制，滑块取图后末尾追加 2 位 [0-9a-z]，共 34 位
+/// - 格式不对时返回 InvalidInput，省去一次注定失败的网络请求
+pub fn validate_gt_challenge(gt: &str, challenge: &str) -> Result<()> {
+    if gt.len() != GT_LEN || !gt.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(invalid_input(&format!(
+            "gt 应为 {} 位十六进制字符串（当前长度 {}）",
+            GT_LEN,
+            gt.len()
+        )));
+    }
+    let valid_challenge = match challenge.len() {
+        GT_LEN => challenge.bytes().all(|b| b.is_ascii_hexdigit()),
+        REFRESHED_CHALLENGE_LEN => {
+            let (hex, suffix) = challenge.split_at(GT_LEN);
+            hex.bytes().all(|b| b.is_ascii_hexdigit())
+                && suffix.bytes().all(|b| b.is_ascii_digit() || b.is_ascii_lowercase())
+        }
+        _ => false,
+    };
+    if !valid_challenge {
+        return Err(invalid_input(&format!(
+            "challenge 应为 {} 或 {} 位十六进制字符串（当前长度 {}）",
+            GT_LEN,
+            REFRESHED_CHALLENGE_LEN,
+            challenge.len()
+        )));
+    }
+    Ok(())
+}
+
+/// ### 生成 jsonp 动态回调名
+/// - 系统时间早于 1970 年时以 0 作为时间戳，不会 panic
+pub(crate) fn jsonp_callback() -> String {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    format!("geetest_{}", timestamp)
+}
+
+/// ### 附加调用方传入的 Cookie 请求头
+/// - 用于续接在其他流程中开始的验证
+pub(crate) fn with_cookies(builder: RequestBuilder, cookies: Option<&str>) -> RequestBuilder {
+    match cookies {
+        Some(cookies) => builder.header(reqwest::header::COOKIE, cookies),
+        None => builder,
+    }
+}
+
+/// ### 附加调用方传入的 Referer 请求头
+/// - 部分站点只在带有自身页面地址的 Referer 时才下发验证码配置
+pub(crate) fn with_referer(builder: RequestBuilder, referer: Option<&str>) -> RequestBuilder {
+    match referer {
+        Some(referer) => builder.header(reqwest::header::REFERER, referer),
+        None => builder,
+    }
+}
+
+/// ### 发送极验请求并读取响应文本
+/// - 设置了 traffic 时录制响应，回放模式下直接读取录制而不访问网络
+/// - 非 2xx 状态返回 Upstream 错误，带上截断后的响应体；带封禁标记的 403 视为出口 IP 被封禁（见 `is_ban_response`），429 为限流
+pub(crate) fn send_text(builder: RequestBuilder, traffic: Option<&Traffic>) -> Result<String> {
+    let (client, request) = builder.build_split();
+    let request = request.map_err(net_work_error)?;
+    let url = request.url().clone();
+    // 回放时没有响应头，不提供等待时间
+    let mut retry_after = None;
+    let (status, text) = match traffic {
+        Some(traffic) if traffic.is_replay() => traffic.load(&url)?,
+        _ => {
+            let res = client.execute(request).map_err(net_work_error)?;
+            let status = res.status().as_u16();
+            retry_after = retry_after_header(res.headers());
+            let text = res.text().map_err(net_work_error)?;
+            if let Some(traffic) = traffic {
+                traffic.save(&url, status, &text);
+            }
+            (status, text)
+        }
+    };
+    upstream_text(status, text, retry_after)
+}
+
+/// ### 异步发送极验请求并读取响应文本
+/// - 与 `send_text` 相同
+#[cfg(feature = "async-client")]
+pub(crate) async fn send_text_async(
+    builder: reqwest::RequestBuilder,
+    traffic: Option<&Traffic>,
+) -> Result<String> {
+    let (client, request) = builder.build_split();
+    let request = request.map_err(net_work_error)?;
+    let url = request.url().clone();
+    // 回放时没有响应头，不提供等待时间
+    let mut retry_after = None;
+    let (status, text) = match traffic {
+        Some(traffic) if traffic.is_replay() => traffic.load(&url)?,
+        _ => {
+            let res = client.execute(request).await.map_err(net_work_error)?;
+            let status = res.status().as_u16();
+            retry_after = retry_after_header(res.headers());
+            let text = res.text().await.map_err(net_work_error)?;
+            if let Some(traffic) = traffic {
+                traffic.save(&url, status, &text);
+            }
+            (status, text)
+        }
+    };
+    upstream_text(status, text, retry_after)
+}
+
+fn upstream_text(status: u16, text: String, retry_after: Option<Duration>) -> Result<String> {
+    if status == 403 && is_ban_response(&text, retry_after) {
+        return Err(banned(retry_after));
+    }
+    if status == 429 {
+        return Err(rate_limited(retry_after));
+    }
+    if !(200..300).contains(&status) {
+        return Err(upstream_status(status, &text));
+    }
+    Ok(text)
+}
+
+/// 解析以秒为单位的 Retry-After 响应头，不支持 HTTP 日期格式
+fn retry_after_header(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+/// 极验封禁出口 IP 时 jsonp 错误信息中出现的关键字
+const BAN_MARKERS: [&str; 3] = ["forbidden", "banned", "blocked"];
+
+/// 403 响应体中表示出口 IP 被封禁的关键字；普通的 403 页面也会写 Forbidden，不能作为依据
+const HTTP_BAN_MARKERS: [&str; 2] = ["banned", "blocked"];
+
+/// ### 403 是否为封禁出口 IP
+/// - 带 Retry-After 或响应体中有封禁关键字时视为封禁，由熔断立即隔离代理
+/// - 其他 403（如鉴权失败、路径错误）与别的非 2xx 状态一样返回 Upstream 错误
+fn is_ban_response(body: &str, retry_after: Option<Duration>) -> bool {
+    let lower = body.to_ascii_lowercase();
+    retry_after.is_some() || HTTP_BAN_MARKERS.iter().any(|marker| lower.contains(marker))
+}
+
+/// ### 下载图片
+/// - 与 `send_text` 一样经 traffic 录制或回放
+/// - 响应体超过 limit 字节时中止下载并返回解析错误，避免被代理塞入的超大响应耗尽内存
+fn send_bytes(builder: RequestBuilder, traffic: Option<&Traffic>, limit: u64) -> Result<Vec<u8>> {
+    let (client, request) = builder.build_split();
+    let request = request.map_err(net_work_error)?;
+    let url = request.url().clone();
+    if let Some(traffic) = traffic.filter(|traffic| traffic.is_replay()) {
+        return traffic.load_bytes(&url);
+    }
+    let res = client.execute(request).map_err(net_work_error)?;
+    let too_large = || parse_error(format!("图片超过 {} 字节的下载上限: {}", limit, url));
+    // Content-Length 可能缺失或与实际不符，读取时仍按上限截断
+    if res.content_length().is_some_and(|len| len > limit) {
+        return Err(too_large());
+    }
+    let mut bytes = Vec::new();
+    res.take(limit.saturating_add(1))
+        .read_to_end(&mut bytes)
+        .map_err(net_work_error)?;
+    if bytes.len() as u64 > limit {
+        return Err(too_large());
+    }
+    if let Some(traffic) = traffic {
+        traffic.save_bytes(&url, &bytes);
+    }
+    Ok(bytes)
+}
+
+/// ### 去掉 jsonp 回调包裹并解析为 json
+/// - 容忍首尾空白和末尾的分号，字段顺序不影响解析
+/// - 包裹或 json 不完整、顶层不是对象时返回带响应片段的 ParseError
+/// - 极验以 `{"status": "error", "error": ...}` 拒绝请求时返回 Rejected
+/// - 错误信息表明出口 IP 被封禁时返回 Banned，带上响应中的 retry_after（秒）
+pub(crate) fn parse_jsonp(res: &str, callback: &str) -> Result<Value> {
+    let prefix = format!("{}(", callback);
+    let trimmed = res.trim();
+    let json = trimmed
+        .strip_suffix(';')
+        .unwrap_or(trimmed)
+        .trim_end()
+        .strip_prefix(&prefix)
+        .ok_or_else(|| decode_error("jsonp 前缀错误", res))?
+        .strip_suffix(')')
+        .ok_or_else(|| decode_error("jsonp 后缀错误", res))?;
+    let res: Value = serde_json::from_str(json).map_err(|e| decode_error(e, json))?;
+    if !res.is_object() {
+        return Err(decode_error("响应不是 json 对象", json));
+    }
+    if res.get("status").and_then(Value::as_str) == Some("error") {
+        let reason = res
+            .get("error")
+            .and_then(Value::as_str)
+            .unwrap_or("未知错误");
+        let lower = reason.to_ascii_lowercase();
+        if BAN_MARKERS.iter().any(|marker| lower.contains(marker)) {
+            let retry_after = res.get("retry_after").and_then(Value::as_u64).map(Duration::from_secs);
+            return Err(banned(retry_after));
+        }
+        return Err(rejected(&format!("极验拒绝了请求: {}", reason)));
+    }
+    Ok(res)
+}
+
+/// ### 从 get.php 的响应中取出c和s
+pub(crate) fn parse_c_s(res: &Value) -> Result<(Vec<u8>, String)> {
+    let data = res.get("data").ok_or_else(|| missing_param("data"))?;
+    Ok((parse_c(data)?, parse_s(data)?))
+}
+
+/// ### 取出响应中的s
+/// - 滑块生成 w 时 s 按字节插入轨迹密文，应为非空、偶数长度的十六进制串，格式不符时返回带该字段内容的 ParseError
+pub(crate) fn parse_s(data: &Value) -> Result<String> {
+    let s = data.get("s").ok_or_else(|| missing_param("s"))?;
+    match s.as_str() {
+        Some(hex) if !hex.is_empty() && hex.len() % 2 == 0 && hex.bytes().all(|b| b.is_ascii_hexdigit()) => {
+            Ok(hex.to_string())
+        }
+        _ => Err(decode_error("s 不是偶数长度的十六进制串", &s.to_string())),
+    }
+}
+
+/// ### 取出响应中的c
+/// - c 应为 0-255 的整数数组，类型不符时返回带该字段内容的 ParseError
+pub(crate) fn parse_c(data: &Value) -> Result<Vec<u8>> {
+    let c = data.get("c").ok_or_else(|| missing_param("c"))?;
+    serde_json::from_value(c.clone()).map_err(|e| decode_error(format!("c 格式错误: {}", e), &c.to_string()))
+}
+
+/// ### 异步获取c和s参数
+/// - 与 `Api::get_c_s_timed` 等价，直接 await 网络请求而不占用阻塞线程池
+#[cfg(feature = "async-client")]
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn get_c_s_async(
+    client: &reqwest::Client,
+    traffic: Option<&Traffic>,
+    base_url: &str,
+    gt: &str,
+    challenge: &str,
+    w: Option<&str>,
+    cookies: Option<&str>,
+    referer: Option<&str>,
+) -> Result<(Vec<u8>, String, Timings)> {
+    let mut watch = Stopwatch::start();
+    let callback = jsonp_callback();
+
+    let url = endpoint(base_url, "get.php");
+    let mut params = HashMap::from([
+        ("gt", gt),
+        ("challenge", challenge),
+        ("callback", callback.as_str()),
+    ]);
+    if let Some(w) = w {
+        params.insert("w", w);
+    }
+    let mut builder = client.get(url);
+    if let Some(cookies) = cookies {
+        builder = builder.header(reqwest::header::COOKIE, cookies);
+    }
+    if let Some(referer) = referer {
+        builder = builder.header(reqwest::header::REFERER, referer);
+    }
+    let res = watch.network_async(send_text_async(builder.query(&params), traffic)).await?;
+
+    let (c, s) = parse_c_s(&parse_jsonp(&res, &callback)?)?;
+    Ok((c, s, watch.finish()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::{Rng, SeedableRng};
+    use serde_json::json;
+    use std::io::Write;
+    use std::net::TcpListener;
+
+    /// 只响应一次的 HTTP 桩，依次写出 head（状态行与响应头）和 body
+    fn serve_raw(head: String, body: Vec<u8>) -> String {
+        serve_recording(head, body).0
+    }
+
+    /// 同 serve_raw，另外通过 channel 交出收到的请求（转为小写）
+    fn serve_recording(head: String, body: Vec<u8>) -> (String, std::sync::mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 4096];
+            let n = stream.read(&mut buf).unwrap_or(0);
+            let _ = tx.send(String::from_utf8_lossy(&buf[..n]).to_lowercase());
+            let _ = stream.write_all(head.as_bytes());
+            let _ = stream.write_all(&body);
+        });
+        (format!("http://{}/bg.png", addr), rx)
+    }
+
+    /// 返回 body_len 字节的图片响应；with_length 为 false 时不带 Content-Length
+    fn serve_once(body_len: usize, with_length: bool) -> String {
+        let head = if with_length {
+            format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", body_len)
+        } else {
+            "HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n".to_string()
+        };
+        serve_raw(head, vec![0u8; body_len])
+    }
+
+    #[test]
+    fn oversized_images_are_rejected() {
+        let client = Client::new();
+        let ok = send_bytes(client.get(serve_once(1024, true)), None, 1024).unwrap();
+        assert_eq!(ok.len(), 1024);
+
+        // 按 Content-Length 直接拒绝
+        let err = send_bytes(client.get(serve_once(4096, true)), None, 1024).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ParseFailed);
+        // 没有 Content-Length 时读到上限即中止
+        let err = send_bytes(client.get(serve_once(4096, false)), None, 1024).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ParseFailed);
+    }
+
+    #[test]
+    fn upstream_429_carries_retry_after() {
+        let client = Client::new();
+        let head = "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 3\r\nContent-Length: 4\r\n\r\n";
+        let err = send_text(client.get(serve_raw(head.to_string(), b"slow".to_vec())), None).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::UpstreamRateLimited);
+        assert_eq!(err.retry_after(), Some(Duration::from_secs(3)));
+        assert_eq!(crate::retry::rate_limit_wait(&err), Some(Duration::from_secs(3)));
+        // 其他错误不等待，保持立即重试
+        assert_eq!(crate::retry::rate_limit_wait(&banned(None)), None);
+    }
+
+    #[test]
+    fn only_marked_403_counts_as_a_ban() {
+        let client = Client::new();
+        let forbidden = |head: &str, body: &str| {
+            send_text(client.get(serve_raw(head.to_string(), body.as_bytes().to_vec())), None).unwrap_err()
+        };
+        let plain = forbidden("HTTP/1.1 403 Forbidden\r\nContent-Length: 13\r\n\r\n", "403 Forbidden");
+        assert_eq!(plain.code(), ErrorCode::UpstreamStatus);
+
+        let marked = forbidden("HTTP/1.1 403 Forbidden\r\nContent-Length: 10\r\n\r\n", "IP BLOCKED");
+        assert_eq!(marked.code(), ErrorCode::IpBanned);
+
+        let err = forbidden("HTTP/1.1 403 Forbidden\r\nRetry-After: 60\r\nContent-Length: 2\r\n\r\n", "no");
+        assert_eq!(err.code(), ErrorCode::IpBanned);
+        assert_eq!(err.retry_after(), Some(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn forwarded_cookies_and_referer_are_sent() {
+        let json = |body: &str| {
+            let head = format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n", body.len());
+            serve_recording(head, body.as_bytes().to_vec())
+        };
+        let client = std::sync::Arc::new(Client::new());
+        let mut slide = crate::slide::Slide::new(std::sync::Arc::clone(&client), client);
+        slide.set_cookies(Some("geetest=1".to_string()));
+        slide.set_target_cookies(Some("SESSDATA=abc".to_string()));
+        slide.set_referer(Some("https://example.com/page".to_string()));
+
+        let (url, requests) = json(r#"{"gt": "g", "challenge": "c"}"#);
+        let res = slide.register_test(&url).unwrap();
+        assert_eq!(res, ("g".to_string(), "c".to_string()));
+        let request = requests.recv().unwrap();
+        assert!(request.contains("cookie: sessdata=abc\r\n"));
+        assert!(request.contains("referer: https://example.com/page\r\n"));
+        // 发给极验的 Cookie 不会带到目标站点
+        assert!(!request.contains("geetest=1"));
+
+        // 极验接口带上调用方的 Cookie 与同一个 Referer，响应是否可解析不影响请求头
+        let (url, requests) = json("{}");
+        slide.set_base_url(url.trim_end_matches("/bg.png"));
+        let _ = slide.refresh_challenge("g", "c");
+        let request = requests.recv().unwrap();
+        assert!(request.starts_with("get /refresh.php?"));
+        assert!(request.contains("cookie: geetest=1\r\n"));
+        assert!(request.contains("referer: https://example.com/page\r\n"));
+        assert!(!request.contains("sessdata"));
+    }
+
+    #[test]
+    fn malformed_jsonp_reports_a_snippet() {
+        let message = |err: Error| std::error::Error::source(&err).unwrap().to_string();
+        // 字段顺序不同、末尾带分号和换行时照常解析
+        let reordered = "cb({\"s\": \"abc\", \"data\": {\"s\": \"3f2e\", \"c\": [1, 2]}});\n";
+        let res = parse_jsonp(reordered, "cb").unwrap();
+        assert_eq!(parse_c_s(&res).unwrap(), (vec![1, 2], "3f2e".to_string()));
+
+        // 截断的响应
+        let err = parse_jsonp("cb({\"data\": {\"c\": [1,", "cb").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ParseFailed);
+        assert!(message(err).contains("cb({\"data\": {\"c\": [1,"));
+        let err = parse_jsonp("cb({\"data\": {\"c\": [1,)", "cb").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ParseFailed);
+        // 片段截断到上限
+        let long = format!("other({})", "x".repeat(4096));
+        let err = parse_jsonp(&long, "cb").unwrap_err();
+        assert!(message(err).chars().count() < 300);
+        // 顶层不是对象
+        let err = parse_jsonp("cb([1, 2])", "cb").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ParseFailed);
+
+        // c 的类型不符
+        let err = parse_c(&serde_json::json!({ "c": [1, 300] })).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ParseFailed);
+        assert!(message(err).contains("[1,300]"));
+        assert_eq!(parse_c(&serde_json::json!({})).unwrap_err().code(), ErrorCode::MissingParam);
+    }
+
+    #[test]
+    fn malformed_s_is_a_decode_error() {
+        let parse = |s: Value| parse_c_s(&serde_json::json!({ "data": { "c": [1, 2], "s": s } }));
+        for s in [json!(""), json!("3"), json!("3f2"), json!("zz"), json!("é1"), json!("3fé"), json!(12), json!(null)] {
+            let err = parse(s.clone()).unwrap_err();
+            assert_eq!(err.code(), ErrorCode::ParseFailed, "{}", s);
+        }
+        assert_eq!(parse_s(&json!({})).unwrap_err().code(), ErrorCode::MissingParam);
+
+        // 随机生成的 s: 只有非空、偶数长度的十六进制串被接受，其余都返回错误而不是 panic
+        let mut rng = StdRng::seed_from_u64(93);
+        for _ in 0..2000 {
+            let len = rng.gen_range(0..8);
+            let s: String = (0..len)
+                .map(|_| *['0', '9', 'a', 'F', 'g', 'é', '中', ' ', '\0'].choose(&mut rng).unwrap())
+                .collect();
+            let valid = !s.is_empty() && s.len() % 2 == 0 && s.bytes().all(|b| b.is_ascii_hexdigit());
+            assert_eq!(parse(json!(s)).is_ok(), valid, "{:?}", s);
+        }
+    }
+}
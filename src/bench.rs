@@ -0,0 +1,199 @@
+// bench.rs
+
+use crate::abstraction::{Test, VerifyType};
+use crate::click::Click;
+use crate::client::{ClientManager, ClientSpec};
+use crate::config::{self, Config};
+use crate::slide::Slide;
+use crate::traffic::Traffic;
+use reqwest::blocking::Client;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 一次求解的耗时与结果，失败时为错误码
+type Sample = (Duration, Result<(), &'static str>);
+
+/// ### 压测
+/// - `--bench` 启动时不监听端口，起若干线程在给定时长内反复完整求解（Test::test），结束后打印吞吐、延迟分位数与错误率
+/// - 并发数、时长与类型见 `config::bench_options`，申请地址同自检
+/// - 设置 GT_REPLAY_DIR 时回放录制的流量，不访问极验，得到可复现的本地求解能力；GEETEST_BASE_URL 可指向模拟服务
+/// - 没有一次成功时返回 false，由调用方以非零状态码退出
+pub(crate) fn run() -> bool {
+    let (config, options) = match Config::load().and_then(|config| Ok((config, config::bench_options()?))) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            println!("压测失败: {}", e);
+            return false;
+        }
+    };
+    let manager = ClientManager::new(config::client_options());
+    let client = match manager.get(&ClientSpec::default()) {
+        Ok(client) => client,
+        Err(e) => {
+            println!("压测失败: 创建客户端失败: {}", e);
+            return false;
+        }
+    };
+    let traffic = config.traffic().map(Arc::new);
+    println!(
+        "压测 {}: 并发 {}，时长 {}s，申请地址 {}",
+        options.kind.as_str(),
+        options.concurrency,
+        options.duration.as_secs(),
+        options.url
+    );
+
+    let started = Instant::now();
+    let deadline = started + options.duration;
+    let samples: Vec<Sample> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..options.concurrency)
+            .map(|_| {
+                let solver = Solver::new(options.kind, &config, Arc::clone(&client), traffic.clone());
+                scope.spawn(|| solver.run(&options.url, deadline))
+            })
+            .collect();
+        workers.into_iter().flat_map(|worker| worker.join().unwrap_or_default()).collect()
+    });
+
+    let report = Report::new(&samples, started.elapsed());
+    report.print();
+    report.solved > 0
+}
+
+/// 每个线程一个求解实例，按类型区分
+enum Solver {
+    Click(Click),
+    Slide(Slide),
+}
+
+impl Solver {
+    /// 与服务端新建实例的方式相同: 配置的极验地址、流量回放与图片大小上限
+    fn new(kind: VerifyType, config: &Config, client: Arc<Client>, traffic: Option<Arc<Traffic>>) -> Self {
+        if kind == VerifyType::Click {
+            let mut click = Click::new(Arc::clone(&client), client);
+            click.set_base_url(config.geetest_base_url.as_str());
+            click.set_traffic(traffic);
+            click.set_max_image_bytes(config.max_image_bytes);
+            Solver::Click(click)
+        } else {
+            let mut slide = Slide::new(Arc::clone(&client), client);
+            slide.set_base_url(config.geetest_base_url.as_str());
+            slide.set_traffic(traffic);
+            slide.set_max_image_bytes(config.max_image_bytes);
+            Solver::Slide(slide)
+        }
+    }
+
+    /// 反复求解直到截止时间，已开始的求解会完成后再返回
+    fn run(mut self, url: &str, deadline: Instant) -> Vec<Sample> {
+        let mut samples = Vec::new();
+        while Instant::now() < deadline {
+            let started = Instant::now();
+            let res = match &mut self {
+                Solver::Click(click) => click.test(url),
+                Solver::Slide(slide) => slide.test(url),
+            };
+            samples.push((started.elapsed(), res.map(|_| ()).map_err(|e| e.code().as_str())));
+        }
+        samples
+    }
+}
+
+/// ### 压测结果
+/// - 延迟分位数只统计成功的求解，失败往往很快返回，混在一起会让延迟看起来偏低
+struct Report {
+    attempts: usize,
+    solved: usize,
+    elapsed: Duration,
+    p50: Duration,
+    p95: Duration,
+    p99: Duration,
+    /// 各错误码的次数
+    errors: BTreeMap<&'static str, usize>,
+}
+
+impl Report {
+    fn new(samples: &[Sample], elapsed: Duration) -> Self {
+        let mut latencies: Vec<Duration> = samples
+            .iter()
+            .filter(|(_, res)| res.is_ok())
+            .map(|(latency, _)| *latency)
+            .collect();
+        latencies.sort_unstable();
+        let mut errors = BTreeMap::new();
+        for (_, res) in samples {
+            if let Err(code) = res {
+                *errors.entry(*code).or_insert(0) += 1;
+            }
+        }
+        Report {
+            attempts: samples.len(),
+            solved: latencies.len(),
+            elapsed,
+            p50: percentile(&latencies, 0.50),
+            p95: percentile(&latencies, 0.95),
+            p99: percentile(&latencies, 0.99),
+            errors,
+        }
+    }
+
+    fn print(&self) {
+        let secs = self.elapsed.as_secs_f64().max(f64::EPSILON);
+        let error_rate = if self.attempts == 0 {
+            0.0
+        } else {
+            (self.attempts - self.solved) as f64 / self.attempts as f64
+        };
+        println!(
+            "求解 {} 次，成功 {} 次，耗时 {:.1}s，吞吐 {:.2} 次/s（成功 {:.2} 次/s），错误率 {:.1}%",
+            self.attempts,
+            self.solved,
+            secs,
+            self.attempts as f64 / secs,
+            self.solved as f64 / secs,
+            error_rate * 100.0
+        );
+        println!(
+            "成功求解延迟: p50 {:.3}s，p95 {:.3}s，p99 {:.3}s",
+            self.p50.as_secs_f64(),
+            self.p95.as_secs_f64(),
+            self.p99.as_secs_f64()
+        );
+        for (code, count) in &self.errors {
+            println!("  [{}] {} 次", code, count);
+        }
+    }
+}
+
+/// 已排序样本的分位数（最近秩），没有样本时为 0
+fn percentile(sorted: &[Duration], q: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (q * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_counts_errors_and_uses_only_successful_latencies() {
+        let ms = Duration::from_millis;
+        let mut samples: Vec<Sample> = (1..=100).map(|i| (ms(i), Ok(()))).collect();
+        samples.push((ms(1), Err("rejected")));
+        samples.push((ms(2), Err("rejected")));
+        samples.push((ms(5000), Err("upstream_timeout")));
+
+        let report = Report::new(&samples, Duration::from_secs(10));
+        assert_eq!((report.attempts, report.solved), (103, 100));
+        assert_eq!((report.p50, report.p95, report.p99), (ms(50), ms(95), ms(99)));
+        assert_eq!(report.errors.get("rejected"), Some(&2));
+        assert_eq!(report.errors.get("upstream_timeout"), Some(&1));
+
+        let empty = Report::new(&[], Duration::from_secs(1));
+        assert_eq!((empty.attempts, empty.p99), (0, Duration::ZERO));
+    }
+}
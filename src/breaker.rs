@@ -0,0 +1,132 @@
+// breaker.rs
+
+use crate::error::{Error, Result};
+use crate::sync::lock_or_recover;
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 最多跟踪的代理数量，超出后淘汰最久未使用的状态
+const MAX_PROXIES: usize = 4096;
+
+#[derive(Default)]
+struct ProxyState {
+    /// 连续失败次数
+    failures: u32,
+    /// 熔断截止时间
+    open_until: Option<Instant>,
+}
+
+/// ### 按代理的熔断器
+/// - 同一代理连续失败 threshold 次后熔断 cooldown，期间直接拒绝，不再发起网络请求
+/// - 冷却结束后放行请求: 成功则恢复，再次失败则立即重新熔断
+/// - threshold 为 0 时关闭熔断
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    states: Mutex<LruCache<String, ProxyState>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            states: Mutex::new(LruCache::new(NonZeroUsize::new(MAX_PROXIES).unwrap())),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.threshold > 0
+    }
+
+    /// ### 检查代理是否处于熔断中
+    /// #### 返回值
+    /// - 熔断中时返回剩余冷却时间
+    pub fn check(&self, key: &str) -> Option<Duration> {
+        if !self.enabled() {
+            return None;
+        }
+        let mut states = lock_or_recover(&self.states, "熔断器");
+        let open_until = states.get(key)?.open_until?;
+        open_until.checked_duration_since(Instant::now()).filter(|d| !d.is_zero())
+    }
+
+    pub fn record_success(&self, key: &str) {
+        if !self.enabled() {
+            return;
+        }
+        let mut states = lock_or_recover(&self.states, "熔断器");
+        if let Some(state) = states.pop(key) {
+            if state.failures >= self.threshold {
+                tracing::info!("代理熔断已恢复");
+            }
+        }
+    }
+
+    pub fn record_failure(&self, key: &str) {
+        if !self.enabled() {
+            return;
+        }
+        let mut states = lock_or_recover(&self.states, "熔断器");
+        let state = states.get_or_insert_mut(key.to_string(), ProxyState::default);
+        state.failures = state.failures.saturating_add(1);
+        if state.failures >= self.threshold {
+            if state.failures == self.threshold {
+                tracing::warn!("代理连续失败 {} 次，熔断 {:?}", state.failures, self.cooldown);
+            }
+            state.open_until = Some(Instant::now() + self.cooldown);
+        }
+    }
+
+    /// ### 隔离被封禁的代理
+    /// - 不等连续失败次数，直接熔断 max(cooldown, retry_after)
+    pub fn quarantine(&self, key: &str, retry_after: Option<Duration>) {
+        if !self.enabled() {
+            return;
+        }
+        let duration = retry_after.map_or(self.cooldown, |d| d.max(self.cooldown));
+        let mut states = lock_or_recover(&self.states, "熔断器");
+        let state = states.get_or_insert_mut(key.to_string(), ProxyState::default);
+        state.failures = state.failures.max(self.threshold);
+        state.open_until = Some(Instant::now() + duration);
+        tracing::warn!("代理出口 IP 被极验封禁，隔离 {:?}", duration);
+    }
+}
+
+/// ### 单个代理的熔断句柄
+/// - 由 `ClientManager::breaker` 取得，求解结束后用 `record` 回报结果
+#[derive(Clone)]
+pub struct ProxyBreaker {
+    breaker: Arc<CircuitBreaker>,
+    key: String,
+}
+
+impl ProxyBreaker {
+    pub(crate) fn new(breaker: Arc<CircuitBreaker>, key: String) -> Self {
+        Self { breaker, key }
+    }
+
+    /// 代理是否处于熔断中
+    pub fn is_open(&self) -> bool {
+        self.breaker.check(&self.key).is_some()
+    }
+
+    /// ### 回报一次请求结果
+    /// - 网络层错误和超时计为失败
+    /// - 出口 IP 被封禁时立即隔离
+    /// - 成功或极验返回的业务错误说明代理可用，清零失败次数
+    pub fn record<T>(&self, result: &Result<T>) {
+        self.record_error(result.as_ref().err());
+    }
+
+    /// 同 `record`，只有错误可用时调用，None 表示成功
+    pub fn record_error(&self, error: Option<&Error>) {
+        match error {
+            Some(e) if e.is_banned() => self.breaker.quarantine(&self.key, e.retry_after()),
+            Some(e) if e.is_transient() => self.breaker.record_failure(&self.key),
+            _ => self.breaker.record_success(&self.key),
+        }
+    }
+}
@@ -1,35 +1,113 @@
 // click.rs
 
-use crate::abstraction::{Api, GenerateW, Test, VerifyType};
+use crate::abstraction::{
+    jsonp_callback, parse_c, parse_jsonp, send_text, verify_payload, with_cookies, with_referer, Api,
+    CapturedImage, GenerateW, Test, VerifyPayload, VerifyType, DEFAULT_BASE_URL,
+    DEFAULT_MAX_IMAGE_BYTES,
+};
+use crate::breaker::ProxyBreaker;
+use crate::client::ConnStats;
 use crate::error::{
-    missing_param, net_work_error, other, other_without_source, parse_error, Result,
+    exhausted, missing_param, net_work_error, other, other_without_source, parse_error, rejected,
+    Error, Result,
 };
-use crate::w::click_calculate;
+use crate::retry::{rate_limit_wait, RetryPolicy};
+use crate::timing::{Stopwatch, Timings};
+use crate::traffic::Traffic;
+use crate::w::{self, WDebug, WOptions};
 use captcha_breaker::captcha::ChineseClick0;
 use captcha_breaker::environment::CaptchaEnvironment;
 use once_cell::sync::Lazy;
 use reqwest::blocking::Client;
+use schemars::JsonSchema;
+use serde::Serialize;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::thread::sleep;
-// 修改：引入 SystemTime 和 UNIX_EPOCH 用于生成时间戳
-use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant};
 
 static GLOBAL_CLICK_BREAKER: Lazy<Arc<ChineseClick0>> = Lazy::new(|| {
-    println!("Loading ChineseClick0 ONNX model... This should only happen once.");
+    tracing::info!("正在加载 ChineseClick0 ONNX 模型，只会加载一次");
     let env = CaptchaEnvironment::default();
     let breaker = env.load_captcha_breaker::<ChineseClick0>().unwrap();
-    println!("Model loaded successfully.");
+    tracing::info!("模型加载完成");
     Arc::new(breaker)
 });
 
+/// ### solve 的步骤
+/// - get_c_s 包含取验证类型与取图，generate_w 包含识别图片计算 key
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SolveStep {
+    RegisterTest,
+    #[serde(rename = "get_c_s")]
+    GetCS,
+    GenerateW,
+    Verify,
+}
+
+/// ### solve 已得到的中间值
+/// - 失败时只有失败步骤之前的字段有值
+#[derive(Clone, Debug, Default, Serialize, JsonSchema)]
+pub struct SolveProgress {
+    pub gt: Option<String>,
+    pub challenge: Option<String>,
+    pub c: Option<Vec<u8>>,
+    pub s: Option<String>,
+    pub w: Option<String>,
+    pub validate: Option<String>,
+}
+
+/// ### solve 失败
+/// - step: 失败的步骤
+/// - progress: 失败前得到的中间值
+#[derive(Debug)]
+pub struct SolveFailure {
+    pub step: SolveStep,
+    pub error: Error,
+    pub progress: SolveProgress,
+}
+
+/// ### 识别出的点击位置
+/// - x / y 为模型输出的像素坐标，编码进 key 前尚未换算
+/// - confidence 为模型给出的置信度，当前模型不提供，始终为 None
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, JsonSchema)]
+pub struct ClickPoint {
+    pub x: f32,
+    pub y: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
+}
+
 #[derive(Clone)]
 pub struct Click {
     client: Arc<Client>,
     noproxy_client: Arc<Client>,
+    /// 当前代理的连接统计，由服务端按代理设置
+    conn_stats: Option<Arc<ConnStats>>,
+    /// 当前代理的熔断句柄，由服务端按实际使用的代理设置
+    breaker: Option<ProxyBreaker>,
+    /// 当前客户端使用的代理，已去掉认证信息，由服务端设置
+    proxy_label: Option<String>,
+    /// 图片下载大小上限（字节）
+    max_image_bytes: u64,
     verify_type: VerifyType,
+    cookies: Option<String>,
+    /// 目标站点的 Cookie 请求头，只附加到 register_test 请求上
+    target_cookies: Option<String>,
+    referer: Option<String>,
+    base_url: String,
+    traffic: Option<Arc<Traffic>>,
+    w_options: WOptions,
+    /// 为 true 时保留下载的图片
+    capture_images: bool,
+    captured_images: Vec<CapturedImage>,
+    /// 最近一次 calculate_key 识别出的点击位置
+    last_points: Vec<ClickPoint>,
     cb: Arc<ChineseClick0>,
+    #[cfg(feature = "async-client")]
+    async_client: Option<reqwest::Client>,
 }
 
 impl Click {
@@ -37,8 +115,23 @@ impl Click {
         Click {
             client,
             noproxy_client,
+            conn_stats: None,
+            breaker: None,
+            proxy_label: None,
+            max_image_bytes: DEFAULT_MAX_IMAGE_BYTES,
             verify_type: VerifyType::Click,
+            cookies: None,
+            target_cookies: None,
+            referer: None,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            traffic: None,
+            w_options: WOptions::default(),
+            capture_images: false,
+            captured_images: Vec::new(),
+            last_points: Vec::new(),
             cb: Arc::clone(&GLOBAL_CLICK_BREAKER),
+            #[cfg(feature = "async-client")]
+            async_client: None,
         }
     }
 
@@ -46,6 +139,208 @@ impl Click {
         self.client = new_client;
     }
 
+    /// 替换不走代理的客户端（用于下载图片等请求）
+    pub fn update_noproxy_client(&mut self, new_client: Arc<Client>) {
+        self.noproxy_client = new_client;
+    }
+
+    /// 设置图片下载大小上限（字节），默认 `DEFAULT_MAX_IMAGE_BYTES`
+    pub fn set_max_image_bytes(&mut self, limit: u64) {
+        self.max_image_bytes = limit;
+    }
+
+    /// 设置当前客户端使用的代理，应去掉认证信息，不走代理时为 None；只用于展示，不影响请求
+    pub fn set_proxy_label(&mut self, label: Option<String>) {
+        self.proxy_label = label;
+    }
+
+    /// 当前客户端使用的代理，不走代理时为 None
+    pub fn proxy_label(&self) -> Option<&str> {
+        self.proxy_label.as_deref()
+    }
+
+    /// 设置当前代理的连接统计，每次经 client() 发出同步请求时计数
+    pub fn set_conn_stats(&mut self, stats: Option<Arc<ConnStats>>) {
+        self.conn_stats = stats;
+    }
+
+    /// 设置当前代理的熔断句柄，服务端据此回报求解结果
+    pub fn set_breaker(&mut self, breaker: Option<ProxyBreaker>) {
+        self.breaker = breaker;
+    }
+
+    /// 设置极验接口地址，替换默认的 `DEFAULT_BASE_URL`
+    pub fn set_base_url(&mut self, base_url: impl Into<String>) {
+        self.base_url = base_url.into();
+    }
+
+    /// 设置极验请求的录制或回放，None 时直接访问网络
+    pub fn set_traffic(&mut self, traffic: Option<Arc<Traffic>>) {
+        self.traffic = traffic;
+    }
+
+    /// 设置附加到极验请求上的 Cookie 请求头，空字符串视为未设置
+    pub fn set_cookies(&mut self, cookies: Option<String>) {
+        self.cookies = cookies.filter(|c| !c.trim().is_empty());
+    }
+
+    /// 设置目标站点的 Cookie 请求头，用于需要登录才能申请验证码的站点；只发往 register_test 的地址，不发给极验
+    pub fn set_target_cookies(&mut self, cookies: Option<String>) {
+        self.target_cookies = cookies.filter(|c| !c.trim().is_empty());
+    }
+
+    /// 设置 Referer 请求头，附加到 register_test 以及之后发往极验的每个请求上，空字符串视为未设置
+    pub fn set_referer(&mut self, referer: Option<String>) {
+        self.referer = referer.filter(|r| !r.trim().is_empty());
+    }
+
+    /// 设置生成 w 时使用的随机数种子，点选忽略其中的轨迹参数
+    pub fn set_w_options(&mut self, options: WOptions) {
+        self.w_options = options;
+    }
+
+    /// 设置生成 w 时使用的算法版本，None 为最新版本；不改动 w_options 中的其他参数
+    pub fn set_algo_version(&mut self, version: Option<u32>) {
+        self.w_options.algo_version = version;
+    }
+
+    /// 设置为 true 时保留求解过程中下载的图片，之后用 `take_images` 取出，同时丢弃之前保留的图片
+    pub fn set_capture_images(&mut self, capture: bool) {
+        self.capture_images = capture;
+        self.captured_images.clear();
+    }
+
+    /// 取出已保留的图片，按下载顺序排列
+    pub fn take_images(&mut self) -> Vec<CapturedImage> {
+        std::mem::take(&mut self.captured_images)
+    }
+
+    /// 最近一次识别出的点击位置，按点击顺序排列；重试时为最后一次尝试的结果
+    pub fn last_points(&self) -> &[ClickPoint] {
+        &self.last_points
+    }
+
+    /// 下载图片，开启 capture_images 时同时保留一份
+    fn fetch_img(&mut self, url: &str) -> Result<Vec<u8>> {
+        let bytes = self.download_img(url)?;
+        if self.capture_images {
+            self.captured_images.push(CapturedImage { url: url.to_string(), bytes: bytes.clone() });
+        }
+        Ok(bytes)
+    }
+
+    /// 设置异步路径使用的客户端
+    #[cfg(feature = "async-client")]
+    pub fn set_async_client(&mut self, client: reqwest::Client) {
+        self.async_client = Some(client);
+    }
+
+    #[cfg(feature = "async-client")]
+    fn async_client(&self) -> Result<&reqwest::Client> {
+        self.async_client
+            .as_ref()
+            .ok_or_else(|| other_without_source("异步客户端未初始化"))
+    }
+
+    /// ### 异步获取c和s参数
+    #[cfg(feature = "async-client")]
+    pub async fn get_c_s_async(
+        &self,
+        gt: &str,
+        challenge: &str,
+        w: Option<&str>,
+    ) -> Result<(Vec<u8>, String)> {
+        self.get_c_s_timed_async(gt, challenge, w).await.map(|(c, s, _)| (c, s))
+    }
+
+    /// ### 异步获取c和s参数并统计耗时
+    #[cfg(feature = "async-client")]
+    pub async fn get_c_s_timed_async(
+        &self,
+        gt: &str,
+        challenge: &str,
+        w: Option<&str>,
+    ) -> Result<(Vec<u8>, String, Timings)> {
+        self.record_request();
+        crate::abstraction::get_c_s_async(self.async_client()?, self.traffic(), &self.base_url, gt, challenge, w, self.cookies(), self.referer()).await
+    }
+
+    /// ### 异步验证
+    /// #### 返回值
+    /// - message
+    /// - validate
+    #[cfg(feature = "async-client")]
+    pub async fn verify_async(
+        &self,
+        gt: &str,
+        challenge: &str,
+        w: Option<&str>,
+    ) -> Result<(String, String)> {
+        self.verify_full_async(gt, challenge, w)
+            .await
+            .map(|(message, validate, _)| (message, validate))
+    }
+
+    /// ### 异步验证并保留极验的完整响应
+    /// #### 返回值
+    /// - message
+    /// - validate
+    /// - 完整验证结果
+    #[cfg(feature = "async-client")]
+    pub async fn verify_full_async(
+        &self,
+        gt: &str,
+        challenge: &str,
+        w: Option<&str>,
+    ) -> Result<(String, String, VerifyPayload)> {
+        self.verify_full_timed_async(gt, challenge, w)
+            .await
+            .map(|(message, validate, payload, _)| (message, validate, payload))
+    }
+
+    /// ### 异步验证并统计耗时
+    #[cfg(feature = "async-client")]
+    pub async fn verify_full_timed_async(
+        &self,
+        gt: &str,
+        challenge: &str,
+        w: Option<&str>,
+    ) -> Result<(String, String, VerifyPayload, Timings)> {
+        let mut watch = Stopwatch::start();
+        let callback = jsonp_callback();
+
+        let url = self.endpoint("ajax.php");
+        let params = verify_params(gt, challenge, callback.as_str(), w);
+        let mut builder = self.async_client()?.get(url);
+        if let Some(cookies) = self.cookies() {
+            builder = builder.header(reqwest::header::COOKIE, cookies);
+        }
+        if let Some(referer) = self.referer() {
+            builder = builder.header(reqwest::header::REFERER, referer);
+        }
+        self.record_request();
+        let res = watch
+            .network_async(crate::abstraction::send_text_async(builder.query(&params), self.traffic()))
+            .await?;
+
+        let (message, validate, payload) = parse_verify(&parse_jsonp(&res, &callback)?, challenge)?;
+        Ok((message, validate, payload, watch.finish()))
+    }
+
+    /// ### 异步生成w
+    /// - 点选的w只依赖本地计算，不涉及网络
+    #[cfg(feature = "async-client")]
+    pub async fn generate_w_async(
+        &self,
+        key: &str,
+        gt: &str,
+        challenge: &str,
+        c: &[u8],
+        s: &str,
+    ) -> Result<String> {
+        self.generate_w(key, gt, challenge, c, s)
+    }
+
     pub fn simple_match(&mut self, gt: &str, challenge: &str) -> Result<String> {
         self.get_c_s(gt, challenge, None)?;
         self.get_type(gt, challenge, None)?;
@@ -63,23 +358,137 @@ impl Click {
         Ok(validate)
     }
 
-    pub fn simple_match_retry(&mut self, gt: &str, challenge: &str) -> Result<String> {
+    /// ### 一键求解，验证失败时刷新图片重试
+    /// - 最多尝试 max_attempts 次（至少 1 次），刷新本身失败时直接返回
+    /// #### 返回值
+    /// - validate
+    /// - 成功的是第几次尝试
+    /// - 次数用完时返回 RetriesExhausted 错误，带上尝试次数，source 为最后一次的错误
+    pub fn simple_match_retry(&mut self, gt: &str, challenge: &str, max_attempts: u32) -> Result<(String, u32)> {
         self.get_c_s(gt, challenge, None)?;
         self.get_type(gt, challenge, None)?;
-        let (c, s, args) = self.get_new_c_s_args(gt, challenge)?;
-
-        if let Ok(result) = self.vvv(gt, challenge, &c, s.as_str(), args) {
-            return Ok(result);
+        let (c, s, mut args) = self.get_new_c_s_args(gt, challenge)?;
+        let max_attempts = max_attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            match self.vvv(gt, challenge, &c, s.as_str(), args) {
+                Ok(validate) => return Ok((validate, attempt)),
+                Err(e) if attempt >= max_attempts => return Err(exhausted(attempt, e)),
+                Err(e) => match rate_limit_wait(&e) {
+                    // 立即重试只会加重限流
+                    Some(wait) => {
+                        tracing::warn!("第 {}/{} 次尝试被极验限流，{:?} 后重试: {}", attempt, max_attempts, wait, e);
+                        sleep(wait);
+                    }
+                    None => tracing::warn!("第 {}/{} 次尝试失败，刷新图片后重试: {}", attempt, max_attempts, e),
+                },
+            }
+            attempt += 1;
+            args = self.refresh(gt, challenge)?;
         }
+    }
 
+    /// ### 带退避重试的 simple_match
+    /// - 只有网络/上游超时等临时错误才会重试，解析错误等直接返回
+    /// #### 返回值
+    /// - validate
+    /// - 实际尝试次数
+    pub fn simple_match_backoff(
+        &mut self,
+        gt: &str,
+        challenge: &str,
+        policy: &RetryPolicy,
+    ) -> Result<(String, u32)> {
+        let mut attempt = 0;
         loop {
-            let args = self.refresh(gt, challenge)?;
-            if let Ok(result) = self.vvv(gt, challenge, &c, s.as_str(), args) {
-                return Ok(result);
+            attempt += 1;
+            match self.simple_match(gt, challenge) {
+                Ok(validate) => return Ok((validate, attempt)),
+                Err(e) if e.is_transient() && attempt <= policy.max_retries => {
+                    let delay = policy.delay(attempt);
+                    tracing::warn!("第 {} 次尝试失败，{:?} 后重试: {}", attempt, delay, e);
+                    sleep(delay);
+                }
+                Err(e) => return Err(e),
             }
         }
     }
 
+    /// ### 依次执行 register_test、get_c_s、generate_w、verify
+    /// - 任一步失败时返回失败的步骤和此前得到的中间值，调用方不必从头重试
+    pub fn solve(&mut self, url: &str) -> std::result::Result<SolveProgress, SolveFailure> {
+        let mut progress = SolveProgress::default();
+        match self.solve_steps(url, &mut progress) {
+            Ok(()) => Ok(progress),
+            Err((step, error)) => Err(SolveFailure { step, error, progress }),
+        }
+    }
+
+    /// ### 依次执行 register_test、get_c_s、generate_w，不调用 verify
+    /// - 供自行向目标站点提交 w 的调用方使用，返回的 validate 始终为空
+    /// - 不等待 verify 前的 2 秒，提交时机由调用方控制
+    pub fn prepare(&mut self, url: &str) -> std::result::Result<SolveProgress, SolveFailure> {
+        let mut progress = SolveProgress::default();
+        match self.prepare_steps(url, &mut progress) {
+            Ok(_) => Ok(progress),
+            Err((step, error)) => Err(SolveFailure { step, error, progress }),
+        }
+    }
+
+    /// ### solve 与 prepare 共用的前三步
+    /// #### 返回值
+    /// - 开始计算 w 的时间，verify 前据此凑满 2 秒
+    fn prepare_steps(
+        &mut self,
+        url: &str,
+        progress: &mut SolveProgress,
+    ) -> std::result::Result<Instant, (SolveStep, Error)> {
+        let (gt, challenge) = self
+            .register_test(url)
+            .map_err(|e| (SolveStep::RegisterTest, e))?;
+        progress.gt = Some(gt.clone());
+        progress.challenge = Some(challenge.clone());
+
+        let (c, s, args) = self
+            .get_c_s(&gt, &challenge, None)
+            .and_then(|_| self.get_type(&gt, &challenge, None))
+            .and_then(|_| self.get_new_c_s_args(&gt, &challenge))
+            .map_err(|e| (SolveStep::GetCS, e))?;
+        progress.c = Some(c.clone());
+        progress.s = Some(s.clone());
+
+        let start = Instant::now();
+        let w = self
+            .calculate_key(args)
+            .and_then(|key| self.generate_w(&key, &gt, &challenge, &c, &s))
+            .map_err(|e| (SolveStep::GenerateW, e))?;
+        progress.w = Some(w);
+        Ok(start)
+    }
+
+    fn solve_steps(
+        &mut self,
+        url: &str,
+        progress: &mut SolveProgress,
+    ) -> std::result::Result<(), (SolveStep, Error)> {
+        let start = self.prepare_steps(url, progress)?;
+        let elapsed = start.elapsed();
+        if elapsed < Duration::from_secs(2) {
+            sleep(Duration::from_secs(2) - elapsed);
+        }
+        // prepare_steps 成功时以下字段都已有值
+        let (gt, challenge, w) = (
+            progress.gt.clone().unwrap_or_default(),
+            progress.challenge.clone().unwrap_or_default(),
+            progress.w.clone(),
+        );
+        let (_, validate) = self
+            .verify(&gt, &challenge, w.as_deref())
+            .map_err(|e| (SolveStep::Verify, e))?;
+        progress.validate = Some(validate);
+        Ok(())
+    }
+
     fn vvv(
         &mut self,
         gt: &str,
@@ -113,9 +522,43 @@ impl Api for Click {
         &self.noproxy_client
     }
 
+    fn max_image_bytes(&self) -> u64 {
+        self.max_image_bytes
+    }
+
+    fn conn_stats(&self) -> Option<&ConnStats> {
+        self.conn_stats.as_deref()
+    }
+
+    fn breaker(&self) -> Option<&ProxyBreaker> {
+        self.breaker.as_ref()
+    }
+
+    fn cookies(&self) -> Option<&str> {
+        self.cookies.as_deref()
+    }
+
+    fn target_cookies(&self) -> Option<&str> {
+        self.target_cookies.as_deref()
+    }
+
+    fn referer(&self) -> Option<&str> {
+        self.referer.as_deref()
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn traffic(&self) -> Option<&Traffic> {
+        self.traffic.as_deref()
+    }
+
     fn register_test(&self, url: &str) -> crate::error::Result<(String, String)> {
-        let res = self.client().get(url).send().map_err(net_work_error)?;
-        let res = res.json::<Value>().expect("解析失败");
+        self.record_request();
+        let builder = with_referer(with_cookies(self.client().get(url), self.target_cookies()), self.referer());
+        let res = builder.send().map_err(net_work_error)?;
+        let res = res.json::<Value>().map_err(parse_error)?;
         let res_data = res
             .get("data")
             .ok_or_else(|| missing_param("data"))?
@@ -142,15 +585,8 @@ impl Api for Click {
         gt: &str,
         challenge: &str,
     ) -> Result<(Vec<u8>, String, Self::ArgsType)> {
-        // 修改：生成动态回调
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("Time went backwards")
-            .as_millis()
-            .to_string();
-        let callback = format!("geetest_{}", timestamp);
-
-        let url = "http://api.geetest.com/get.php";
+        let callback = jsonp_callback();
+
         let mut params = HashMap::from([
             ("gt", gt),
             ("challenge", challenge),
@@ -168,38 +604,12 @@ impl Api for Click {
             ("width", "100%"), 
         ]);
 
-        params.insert(
-            "type",
-            match self.verify_type {
-                VerifyType::Click => "click",
-                VerifyType::Slide => "slide",
-            },
-        );
-        let res = self
-            .client
-            .get(url)
-            .query(&params)
-            .send()
-            .map_err(net_work_error)?;
-        let res = res.text().map_err(|e| other("什么b玩意错误", e))?;
-
-        // 修改：使用动态回调作为前缀
-        let prefix = format!("{}(", callback);
-        let res = res
-            .strip_prefix(&prefix)
-            .ok_or_else(|| other_without_source("前缀错误"))?
-            .strip_suffix(")")
-            .ok_or_else(|| other_without_source("后缀错误"))?;
-
-        let res: Value = serde_json::from_str(res).map_err(parse_error)?;
+        params.insert("type", self.verify_type.as_str());
+        self.record_request();
+        let res = send_text(self.geetest_get("get.php").query(&params), self.traffic())?;
+        let res = parse_jsonp(&res, &callback)?;
         let res_data = res.get("data").ok_or_else(|| missing_param("data"))?;
-        let c: Vec<u8> = serde_json::from_value(
-            res_data
-                .get("c")
-                .ok_or_else(|| missing_param("c"))?
-                .clone(),
-        )
-        .map_err(parse_error)?;
+        let c = parse_c(res_data)?;
         let static_server = res_data
             .get("static_servers")
             .ok_or_else(|| missing_param("static_servers"))?
@@ -231,93 +641,36 @@ impl Api for Click {
         ))
     }
 
-    fn verify(&self, gt: &str, challenge: &str, w: Option<&str>) -> Result<(String, String)> {
+    fn verify_full_timed(
+        &self,
+        gt: &str,
+        challenge: &str,
+        w: Option<&str>,
+    ) -> Result<(String, String, VerifyPayload, Timings)> {
+        let mut watch = Stopwatch::start();
         // 修改：生成动态回调
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("Time went backwards")
-            .as_millis()
-            .to_string();
-        let callback = format!("geetest_{}", timestamp);
-
-        let url = "http://api.geetest.com/ajax.php";
-        let mut params = HashMap::from([
-            ("gt", gt),
-            ("challenge", challenge),
-            ("lang", "zh-cn"),
-            ("pt", "0"),
-            ("client_type", "web"),
-            ("callback", callback.as_str()), // 使用动态回调
-        ]);
-        if let Some(w) = w {
-            params.insert("w", w);
-        }
-        let res = self
-            .client
-            .get(url)
-            .query(&params)
-            .send()
-            .map_err(net_work_error)?;
-        let res = res.text().map_err(|e| other("什么b玩意错误", e))?;
-
-        // 修改：使用动态回调作为前缀
-        let prefix = format!("{}(", callback);
-        let res = res
-            .strip_prefix(&prefix)
-            .ok_or_else(|| other_without_source("前缀错误"))?
-            .strip_suffix(")")
-            .ok_or_else(|| other_without_source("后缀错误"))?;
-
-        let res: Value = serde_json::from_str(res).map_err(parse_error)?;
-        let res_data = res.get("data").ok_or_else(|| missing_param("data"))?;
-        Ok((
-            res_data
-                .get("result")
-                .ok_or_else(|| missing_param("result"))?
-                .as_str()
-                .ok_or_else(|| missing_param("result"))?
-                .to_string(),
-            res_data
-                .get("validate")
-                .ok_or_else(|| missing_param("validate"))?
-                .as_str()
-                .ok_or_else(|| missing_param("validate"))?
-                .to_string(),
-        ))
+        let callback = jsonp_callback();
+
+        let params = verify_params(gt, challenge, callback.as_str(), w);
+        let builder = self.geetest_get("ajax.php").query(&params);
+        self.record_request();
+        let res = watch.network(|| send_text(builder, self.traffic()))?;
+
+        let (message, validate, payload) = parse_verify(&parse_jsonp(&res, &callback)?, challenge)?;
+        Ok((message, validate, payload, watch.finish()))
     }
 
     fn refresh(&self, gt: &str, challenge: &str) -> Result<Self::ArgsType> {
-        // 修改：生成动态回调
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("Time went backwards")
-            .as_millis()
-            .to_string();
-        let callback = format!("geetest_{}", timestamp);
-
-        let url = "http://api.geetest.com/refresh.php";
+        let callback = jsonp_callback();
+
         let params = HashMap::from([
             ("gt", gt),
             ("challenge", challenge),
             ("callback", callback.as_str()), // 使用动态回调
         ]);
-        let res = self
-            .client
-            .get(url)
-            .query(&params)
-            .send()
-            .map_err(net_work_error)?;
-        let res = res.text().map_err(|e| other("什么b玩意错误", e))?;
-
-        // 修改：使用动态回调作为前缀
-        let prefix = format!("{}(", callback);
-        let res = res
-            .strip_prefix(&prefix)
-            .ok_or_else(|| other_without_source("前缀错误"))?
-            .strip_suffix(")")
-            .ok_or_else(|| other_without_source("后缀错误"))?;
-
-        let res: Value = serde_json::from_str(res).map_err(parse_error)?;
+        self.record_request();
+        let res = send_text(self.geetest_get("refresh.php").query(&params), self.traffic())?;
+        let res = parse_jsonp(&res, &callback)?;
         let res_data = res.get("data").ok_or_else(|| missing_param("data"))?;
         let static_server = res_data
             .get("image_servers")
@@ -342,16 +695,61 @@ impl Api for Click {
     }
 }
 
+fn verify_params<'a>(
+    gt: &'a str,
+    challenge: &'a str,
+    callback: &'a str,
+    w: Option<&'a str>,
+) -> HashMap<&'a str, &'a str> {
+    let mut params = HashMap::from([
+        ("gt", gt),
+        ("challenge", challenge),
+        ("lang", "zh-cn"),
+        ("pt", "0"),
+        ("client_type", "web"),
+        ("callback", callback), // 使用动态回调
+    ]);
+    if let Some(w) = w {
+        params.insert("w", w);
+    }
+    params
+}
+
+fn parse_verify(res: &Value, challenge: &str) -> Result<(String, String, VerifyPayload)> {
+    let res_data = res.get("data").ok_or_else(|| missing_param("data"))?;
+    let result = res_data
+        .get("result")
+        .ok_or_else(|| missing_param("result"))?
+        .as_str()
+        .ok_or_else(|| missing_param("result"))?
+        .to_string();
+    if result != "success" {
+        return Err(rejected(&format!("极验验证未通过: {}", result)));
+    }
+    let validate = res_data
+        .get("validate")
+        .ok_or_else(|| missing_param("validate"))?
+        .as_str()
+        .ok_or_else(|| missing_param("validate"))?
+        .to_string();
+    let payload = verify_payload(res_data, challenge, &validate);
+    Ok((result, validate, payload))
+}
+
 impl GenerateW for Click {
     fn calculate_key(&mut self, args: Self::ArgsType) -> Result<String> {
         let pic_url = args;
-        let pic_img = self.download_img(pic_url.as_str())?;
+        let pic_img = self.fetch_img(pic_url.as_str())?;
         let pic_img = image::load_from_memory(&pic_img).map_err(|e| other("图片加载失败", e))?;
 
         let cb_res = self
             .cb
             .run(&pic_img)
             .map_err(|_| other_without_source("cb模块内部错误"))?;
+        self.last_points = cb_res
+            .iter()
+            .map(|&(x, y)| ClickPoint { x, y, confidence: None })
+            .collect();
         let mut res = vec![];
         for (x, y) in &cb_res {
             let position = format!(
@@ -369,10 +767,21 @@ impl GenerateW for Click {
         key: &str,
         gt: &str,
         challenge: &str,
-        _c: &[u8],
-        _s: &str,
+        c: &[u8],
+        s: &str,
     ) -> Result<String> {
-        Ok(click_calculate(key, gt, challenge))
+        w::generate_w(self.verify_type, key, gt, challenge, c, s, &self.w_options)
+    }
+
+    fn generate_w_debug(
+        &self,
+        key: &str,
+        gt: &str,
+        challenge: &str,
+        c: &[u8],
+        s: &str,
+    ) -> Result<(String, WDebug)> {
+        w::generate_w_debug(self.verify_type, key, gt, challenge, c, s, &self.w_options)
     }
 }
 
@@ -0,0 +1,150 @@
+// coalesce.rs
+
+use crate::sync::lock_or_recover;
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    http::{HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+/// 合并后的响应带上的响应头，值为 `true`
+pub(crate) const COALESCED_HEADER: &str = "x-coalesced";
+
+/// ### 相同求解请求的合并
+/// - 同一个键（路由、gt、challenge、session_id）的请求在前一个仍在求解时到达，
+///   直接等待前一个的结果，不再重复请求极验，避免同一 challenge 被并发求解而一起被限流
+/// - 由最先开始的请求求解；它的客户端断开时由等待中的下一个请求接着求解
+/// - 求解完成后立即移除，之后到达的相同请求重新求解
+pub(crate) struct Coalescer {
+    in_flight: Mutex<HashMap<String, Arc<OnceCell<Shared>>>>,
+}
+
+/// 缓存下来供等待者复用的响应
+#[derive(Clone)]
+pub(crate) struct Shared {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl Coalescer {
+    pub(crate) fn new() -> Self {
+        Self { in_flight: Mutex::new(HashMap::new()) }
+    }
+
+    /// 合并键，各部分以不会出现在参数中的 `\0` 分隔
+    pub(crate) fn key(route: &str, gt: &str, challenge: &str, session_id: Option<&str>) -> String {
+        format!("{}\0{}\0{}\0{}", route, gt, challenge, session_id.unwrap_or_default())
+    }
+
+    /// ### 执行或等待求解
+    /// #### 参数
+    /// - `key`: 由 `Coalescer::key` 生成
+    /// - `solve`: 完整的处理过程，只有本请求负责求解时才会被执行
+    /// #### 返回值
+    /// - 求解的响应，等待得到的响应带有 `x-coalesced: true`
+    pub(crate) async fn run<F>(&self, key: String, solve: F) -> Response
+    where
+        F: Future<Output = Response>,
+    {
+        let cell = Arc::clone(
+            lock_or_recover(&self.in_flight, "进行中的求解")
+                .entry(key.clone())
+                .or_insert_with(|| Arc::new(OnceCell::new())),
+        );
+        let mut solved = false;
+        let shared = cell
+            .get_or_init(|| async {
+                solved = true;
+                Shared::buffer(solve.await).await
+            })
+            .await
+            .clone();
+        {
+            let mut in_flight = lock_or_recover(&self.in_flight, "进行中的求解");
+            if in_flight.get(&key).is_some_and(|current| Arc::ptr_eq(current, &cell)) {
+                in_flight.remove(&key);
+            }
+        }
+        let mut response = shared.into_response();
+        if !solved {
+            tracing::info!("相同的求解正在进行，复用其结果");
+            response.headers_mut().insert(COALESCED_HEADER, HeaderValue::from_static("true"));
+        }
+        response
+    }
+}
+
+impl Shared {
+    /// 读出完整响应体，读取失败时为 500
+    pub(crate) async fn buffer(response: Response) -> Self {
+        let (parts, body) = response.into_parts();
+        let body = match to_bytes(body, usize::MAX).await {
+            Ok(body) => body,
+            Err(e) => {
+                tracing::error!("读取求解响应失败: {}", e);
+                return Self { status: StatusCode::INTERNAL_SERVER_ERROR, headers: HeaderMap::new(), body: Bytes::new() };
+            }
+        };
+        Self { status: parts.status, headers: parts.headers, body }
+    }
+
+    pub(crate) fn status(&self) -> StatusCode {
+        self.status
+    }
+}
+
+impl IntoResponse for Shared {
+    fn into_response(self) -> Response {
+        let mut response = Response::new(Body::from(self.body));
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers;
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn concurrent_requests_share_one_solve() {
+        let coalescer = Arc::new(Coalescer::new());
+        let solves = Arc::new(AtomicUsize::new(0));
+        let key = Coalescer::key("/slide/simple_match", "gt", "challenge", None);
+        let requests = (0..3)
+            .map(|_| {
+                let coalescer = Arc::clone(&coalescer);
+                let solves = Arc::clone(&solves);
+                let key = key.clone();
+                tokio::spawn(async move {
+                    coalescer
+                        .run(key, async move {
+                            solves.fetch_add(1, Ordering::SeqCst);
+                            tokio::time::sleep(Duration::from_millis(50)).await;
+                            "validate".into_response()
+                        })
+                        .await
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut coalesced = 0;
+        for request in requests {
+            let response = request.await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            if response.headers().contains_key(COALESCED_HEADER) {
+                coalesced += 1;
+            }
+        }
+        assert_eq!(solves.load(Ordering::SeqCst), 1);
+        assert_eq!(coalesced, 2);
+        // 完成后不再合并
+        assert!(coalescer.in_flight.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,883 @@
+// config.rs
+
+use crate::abstraction::{VerifyType, DEFAULT_BASE_URL, DEFAULT_MAX_IMAGE_BYTES};
+use crate::client::{ClientOptions, ProxyConfig};
+use crate::redact;
+use crate::retry::RetryPolicy;
+use crate::session;
+use crate::traffic::Traffic;
+use axum::http::{HeaderName, HeaderValue, Method};
+use std::net::{IpAddr, SocketAddr};
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::time::Duration;
+use tower_http::cors::{Any, CorsLayer};
+
+/// 默认监听地址（双栈）
+pub(crate) const DEFAULT_BIND: &str = "[::]:3000";
+/// 监听地址环境变量
+pub(crate) const BIND_ENV: &str = "BILITICKER_GT_BIND";
+
+/// ### 极简命令行参数解析
+/// - 支持 `--name value` 与 `--name=value` 两种写法
+pub(crate) fn cli_arg(name: &str) -> Option<String> {
+    let flag = format!("--{}", name);
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == flag {
+            return args.next();
+        }
+        if let Some(value) = arg.strip_prefix(&format!("{}=", flag)) {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+/// ### 监听地址
+/// - `Tcp`: IPv4 `0.0.0.0:3000` 或 IPv6 `[::]:3000`
+/// - `Unix`: `unix:/path/to.sock`，用于挂在 nginx 等反向代理之后
+#[derive(Clone)]
+pub(crate) enum BindAddr {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl std::fmt::Display for BindAddr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BindAddr::Tcp(addr) => write!(f, "http://{}", addr),
+            BindAddr::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// ### 解析监听地址
+/// - 优先级: 环境变量 `BILITICKER_GT_BIND` > `--bind` 参数 > 默认值
+/// - 以 `unix:` 开头时监听 Unix 套接字，否则按 `ip:port` 解析
+/// #### 返回值
+/// - 解析后的地址，解析失败时返回原始字符串和错误信息
+fn resolve_bind() -> Result<BindAddr, String> {
+    let raw = std::env::var(BIND_ENV)
+        .ok()
+        .filter(|s| !s.trim().is_empty())
+        .or_else(|| cli_arg("bind"))
+        .unwrap_or_else(|| DEFAULT_BIND.to_string());
+    let trimmed = raw.trim();
+    if let Some(path) = trimmed.strip_prefix("unix:") {
+        if path.is_empty() {
+            return Err(format!("无效的监听地址 `{}`: Unix 套接字路径为空", raw));
+        }
+        return Ok(BindAddr::Unix(PathBuf::from(path)));
+    }
+    trimmed
+        .parse::<SocketAddr>()
+        .map(BindAddr::Tcp)
+        .map_err(|e| format!("无效的监听地址 `{}`: {}", raw, e))
+}
+
+/// TLS 证书文件环境变量（PEM）
+pub(crate) const TLS_CERT_ENV: &str = "GT_TLS_CERT";
+/// TLS 私钥文件环境变量（PEM）
+pub(crate) const TLS_KEY_ENV: &str = "GT_TLS_KEY";
+
+/// ### TLS 证书与私钥路径
+/// - 两者都设置时直接以 HTTPS 提供服务，都未设置时使用 HTTP
+/// #### 返回值
+/// - 只设置了其中一个时返回错误，避免误以为已启用 HTTPS
+fn tls_paths() -> Result<Option<(PathBuf, PathBuf)>, String> {
+    let path = |name: &str| std::env::var(name).ok().filter(|s| !s.trim().is_empty());
+    match (path(TLS_CERT_ENV), path(TLS_KEY_ENV)) {
+        (Some(cert), Some(key)) => Ok(Some((PathBuf::from(cert), PathBuf::from(key)))),
+        (None, None) => Ok(None),
+        (Some(_), None) => Err(format!("设置了 {} 但未设置 {}，两者需同时设置", TLS_CERT_ENV, TLS_KEY_ENV)),
+        (None, Some(_)) => Err(format!("设置了 {} 但未设置 {}，两者需同时设置", TLS_KEY_ENV, TLS_CERT_ENV)),
+    }
+}
+
+/// 优雅停机等待时间环境变量（秒）
+pub(crate) const SHUTDOWN_GRACE_ENV: &str = "GT_SHUTDOWN_GRACE_SECS";
+/// 默认优雅停机等待时间
+pub(crate) const DEFAULT_SHUTDOWN_GRACE_SECS: u64 = 30;
+
+/// ### 读取数值型环境变量
+/// - 未设置或无法解析时返回默认值，解析失败会打印警告
+pub(crate) fn env_u64(name: &str, default: u64) -> u64 {
+    match std::env::var(name) {
+        Ok(raw) => raw.trim().parse().unwrap_or_else(|_| {
+            tracing::warn!("环境变量 {}={} 无法解析，使用默认值 {}", name, raw, default);
+            default
+        }),
+        Err(_) => default,
+    }
+}
+
+/// 优雅停机时允许进行中的求解继续运行的时间
+fn shutdown_grace() -> Duration {
+    Duration::from_secs(env_u64(SHUTDOWN_GRACE_ENV, DEFAULT_SHUTDOWN_GRACE_SECS))
+}
+
+/// 会话空闲过期时间环境变量（秒）
+pub(crate) const SESSION_TTL_ENV: &str = "GT_SESSION_TTL_SECS";
+/// 默认会话空闲过期时间: 10 分钟
+pub(crate) const DEFAULT_SESSION_TTL_SECS: u64 = 600;
+
+/// 会话空闲超过该时间后会被后台任务清理
+fn session_ttl() -> Duration {
+    Duration::from_secs(env_u64(SESSION_TTL_ENV, DEFAULT_SESSION_TTL_SECS))
+}
+
+/// 连接超时环境变量（毫秒）
+pub(crate) const CONNECT_TIMEOUT_ENV: &str = "GT_CONNECT_TIMEOUT_MS";
+/// 请求总超时环境变量（毫秒）
+pub(crate) const REQUEST_TIMEOUT_ENV: &str = "GT_REQUEST_TIMEOUT_MS";
+
+/// 客户端缓存上限环境变量
+pub(crate) const MAX_CLIENTS_ENV: &str = "GT_MAX_CLIENTS";
+/// 代理连续失败多少次后熔断环境变量，为 0 时关闭熔断
+pub(crate) const BREAKER_THRESHOLD_ENV: &str = "GT_PROXY_BREAKER_THRESHOLD";
+/// 代理熔断持续时间环境变量（秒）
+pub(crate) const BREAKER_COOLDOWN_ENV: &str = "GT_PROXY_BREAKER_COOLDOWN_SECS";
+/// 每个主机最多保留的空闲连接数环境变量
+pub(crate) const POOL_MAX_IDLE_PER_HOST_ENV: &str = "GT_POOL_MAX_IDLE_PER_HOST";
+/// 空闲连接保留时间环境变量（秒），为 0 时不过期
+pub(crate) const POOL_IDLE_TIMEOUT_ENV: &str = "GT_POOL_IDLE_TIMEOUT_SECS";
+/// 构建客户端失败时的重试次数环境变量
+pub(crate) const CLIENT_BUILD_RETRIES_ENV: &str = "GT_CLIENT_BUILD_RETRIES";
+/// 构建客户端重试的基础等待时间环境变量（毫秒）
+pub(crate) const CLIENT_BUILD_RETRY_DELAY_ENV: &str = "GT_CLIENT_BUILD_RETRY_DELAY_MS";
+
+/// 固定 DNS 解析环境变量，格式为 `host=ip,host=ip`
+pub(crate) const DNS_OVERRIDES_ENV: &str = "GT_DNS_OVERRIDES";
+
+/// ### 固定 DNS 解析
+/// - 代理与直连客户端都会设置，只在本地解析目标域名时生效，见 ClientOptions::dns_overrides
+/// - 格式不对的项记录警告后跳过
+fn dns_overrides() -> Vec<(String, IpAddr)> {
+    let Ok(raw) = std::env::var(DNS_OVERRIDES_ENV) else {
+        return Vec::new();
+    };
+    raw.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let parsed = entry
+                .split_once('=')
+                .and_then(|(host, ip)| Some((host.trim(), ip.trim().parse::<IpAddr>().ok()?)))
+                .filter(|(host, _)| !host.is_empty());
+            if parsed.is_none() {
+                tracing::warn!("{} 中的 `{}` 格式不对，应为 host=ip，已忽略", DNS_OVERRIDES_ENV, entry);
+            }
+            parsed.map(|(host, ip)| (host.to_ascii_lowercase(), ip))
+        })
+        .collect()
+}
+
+/// 构建上游客户端使用的参数
+pub(crate) fn client_options() -> ClientOptions {
+    let default = ClientOptions::default();
+    ClientOptions {
+        max_clients: NonZeroUsize::new(env_u64(MAX_CLIENTS_ENV, default.max_clients.get() as u64) as usize)
+            .unwrap_or(default.max_clients),
+        connect_timeout: Duration::from_millis(env_u64(
+            CONNECT_TIMEOUT_ENV,
+            default.connect_timeout.as_millis() as u64,
+        )),
+        request_timeout: Duration::from_millis(env_u64(
+            REQUEST_TIMEOUT_ENV,
+            default.request_timeout.as_millis() as u64,
+        )),
+        breaker_threshold: env_u64(BREAKER_THRESHOLD_ENV, default.breaker_threshold as u64) as u32,
+        breaker_cooldown: Duration::from_secs(env_u64(
+            BREAKER_COOLDOWN_ENV,
+            default.breaker_cooldown.as_secs(),
+        )),
+        pool_max_idle_per_host: env_u64(POOL_MAX_IDLE_PER_HOST_ENV, default.pool_max_idle_per_host as u64)
+            as usize,
+        pool_idle_timeout: Duration::from_secs(env_u64(
+            POOL_IDLE_TIMEOUT_ENV,
+            default.pool_idle_timeout.as_secs(),
+        )),
+        build_retry: RetryPolicy::new(
+            env_u64(CLIENT_BUILD_RETRIES_ENV, default.build_retry.max_retries as u64) as u32,
+            Duration::from_millis(env_u64(
+                CLIENT_BUILD_RETRY_DELAY_ENV,
+                default.build_retry.base_delay.as_millis() as u64,
+            )),
+        ),
+        dns_overrides: dns_overrides(),
+    }
+}
+
+/// 深度健康检查探测地址环境变量
+pub(crate) const HEALTH_PROBE_URL_ENV: &str = "GT_HEALTH_PROBE_URL";
+/// 默认探测地址
+pub(crate) const DEFAULT_HEALTH_PROBE_URL: &str = "http://api.geetest.com/";
+/// 深度健康检查超时环境变量（毫秒）
+pub(crate) const HEALTH_PROBE_TIMEOUT_ENV: &str = "GT_HEALTH_PROBE_TIMEOUT_MS";
+/// 深度健康检查结果缓存时间环境变量（秒）
+pub(crate) const HEALTH_CACHE_ENV: &str = "GT_HEALTH_CACHE_SECS";
+
+/// 深度健康检查: (探测地址, 超时, 缓存时间)
+fn health_probe() -> (String, Duration, Duration) {
+    let url = std::env::var(HEALTH_PROBE_URL_ENV)
+        .ok()
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_HEALTH_PROBE_URL.to_string());
+    (
+        url,
+        Duration::from_millis(env_u64(HEALTH_PROBE_TIMEOUT_ENV, 3000)),
+        Duration::from_secs(env_u64(HEALTH_CACHE_ENV, 5)),
+    )
+}
+
+/// 代理检测地址环境变量，应返回出口 IP（纯文本，或带 ip / origin 字段的 JSON）
+pub(crate) const PROXY_CHECK_URL_ENV: &str = "GT_PROXY_CHECK_URL";
+/// 默认代理检测地址
+pub(crate) const DEFAULT_PROXY_CHECK_URL: &str = "https://api.ipify.org";
+
+/// /proxy/check 请求的 IP 回显地址
+fn proxy_check_url() -> String {
+    std::env::var(PROXY_CHECK_URL_ENV)
+        .ok()
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_PROXY_CHECK_URL.to_string())
+}
+
+/// 每个 session_id 每秒允许的请求数环境变量，为 0 时关闭限流
+pub(crate) const RATE_LIMIT_RPS_ENV: &str = "GT_RATE_LIMIT_RPS";
+/// 每个 session_id 允许的突发请求数环境变量
+pub(crate) const RATE_LIMIT_BURST_ENV: &str = "GT_RATE_LIMIT_BURST";
+/// 默认每秒请求数
+pub(crate) const DEFAULT_RATE_LIMIT_RPS: u64 = 10;
+/// 默认突发请求数
+pub(crate) const DEFAULT_RATE_LIMIT_BURST: u64 = 20;
+
+/// 按 session_id 限流: (每秒请求数, 突发请求数)
+fn rate_limit() -> (u64, u64) {
+    (
+        env_u64(RATE_LIMIT_RPS_ENV, DEFAULT_RATE_LIMIT_RPS),
+        env_u64(RATE_LIMIT_BURST_ENV, DEFAULT_RATE_LIMIT_BURST),
+    )
+}
+
+/// 存活检查失败率阈值环境变量（百分比），为 0 或未设置时关闭
+pub(crate) const LIVENESS_FAILURE_PERCENT_ENV: &str = "GT_LIVENESS_FAILURE_PERCENT";
+/// 存活检查统计窗口环境变量（秒）
+pub(crate) const LIVENESS_WINDOW_SECS_ENV: &str = "GT_LIVENESS_WINDOW_SECS";
+/// 存活检查最少样本数环境变量
+pub(crate) const LIVENESS_MIN_SAMPLES_ENV: &str = "GT_LIVENESS_MIN_SAMPLES";
+/// 默认统计最近 60 秒
+pub(crate) const DEFAULT_LIVENESS_WINDOW_SECS: u64 = 60;
+/// 默认窗口内至少 10 个请求才判定
+pub(crate) const DEFAULT_LIVENESS_MIN_SAMPLES: u64 = 10;
+
+/// ### 存活检查: (失败率阈值, 统计窗口, 最少样本数)
+/// - 窗口内上游失败率超过阈值时 /health 返回 503，见 liveness 模块
+/// - 默认关闭，避免未配置编排探针的部署被误摘除
+fn liveness() -> Option<(f64, Duration, usize)> {
+    let percent = env_u64(LIVENESS_FAILURE_PERCENT_ENV, 0).min(100);
+    (percent > 0).then(|| {
+        (
+            percent as f64 / 100.0,
+            Duration::from_secs(env_u64(LIVENESS_WINDOW_SECS_ENV, DEFAULT_LIVENESS_WINDOW_SECS).max(1)),
+            env_u64(LIVENESS_MIN_SAMPLES_ENV, DEFAULT_LIVENESS_MIN_SAMPLES) as usize,
+        )
+    })
+}
+
+/// 单个 HTTP 请求的处理时限环境变量（毫秒），为 0 或未设置时不限制
+pub(crate) const REQUEST_DEADLINE_MS_ENV: &str = "GT_REQUEST_DEADLINE_MS";
+
+/// 单个 HTTP 请求的处理时限，与每次上游请求的超时相互独立
+fn request_deadline() -> Option<Duration> {
+    Some(env_u64(REQUEST_DEADLINE_MS_ENV, 0))
+        .filter(|&ms| ms > 0)
+        .map(Duration::from_millis)
+}
+
+/// 请求体大小上限环境变量（字节）
+pub(crate) const MAX_BODY_BYTES_ENV: &str = "GT_MAX_BODY_BYTES";
+/// 默认请求体大小上限: 1 MiB
+pub(crate) const DEFAULT_MAX_BODY_BYTES: u64 = 1024 * 1024;
+
+/// 超过该大小的请求体直接返回 413，避免大请求体占满内存
+fn max_body_bytes() -> usize {
+    env_u64(MAX_BODY_BYTES_ENV, DEFAULT_MAX_BODY_BYTES) as usize
+}
+
+/// 响应压缩阈值环境变量（字节）
+pub(crate) const COMPRESSION_MIN_BYTES_ENV: &str = "GT_COMPRESSION_MIN_BYTES";
+/// 默认响应压缩阈值: 1 KiB，更小的响应压缩后几乎不省流量
+pub(crate) const DEFAULT_COMPRESSION_MIN_BYTES: u64 = 1024;
+
+/// 小于该大小的响应不压缩，上限为 u16::MAX
+fn compression_min_bytes() -> u16 {
+    env_u64(COMPRESSION_MIN_BYTES_ENV, DEFAULT_COMPRESSION_MIN_BYTES).min(u16::MAX as u64) as u16
+}
+
+/// 允许跨域的来源环境变量，逗号分隔，`*` 表示任意来源
+pub(crate) const CORS_ORIGINS_ENV: &str = "GT_CORS_ORIGINS";
+/// 允许跨域的方法环境变量，逗号分隔
+pub(crate) const CORS_METHODS_ENV: &str = "GT_CORS_METHODS";
+/// 允许跨域携带的请求头环境变量，逗号分隔
+pub(crate) const CORS_HEADERS_ENV: &str = "GT_CORS_HEADERS";
+/// 设为 1 时放开全部跨域限制，仅用于本地开发
+pub(crate) const CORS_PERMISSIVE_ENV: &str = "GT_CORS_PERMISSIVE";
+/// 默认允许的方法
+const DEFAULT_CORS_METHODS: &str = "GET,POST,DELETE";
+/// 默认允许的请求头
+const DEFAULT_CORS_HEADERS: &str = "content-type,x-request-id,idempotency-key";
+
+/// ### 跨域配置
+/// - `GT_CORS_PERMISSIVE=1` 时放开全部跨域限制
+/// - 否则只允许 `GT_CORS_ORIGINS` 中列出的来源，未设置时拒绝所有跨域请求
+pub(crate) struct CorsConfig {
+    pub(crate) permissive: bool,
+    pub(crate) origins: Option<String>,
+    pub(crate) methods: Option<String>,
+    pub(crate) headers: Option<String>,
+}
+
+impl CorsConfig {
+    fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|s| !s.trim().is_empty());
+        Self {
+            permissive: matches!(var(CORS_PERMISSIVE_ENV).as_deref().map(str::trim), Some("1" | "true")),
+            origins: var(CORS_ORIGINS_ENV),
+            methods: var(CORS_METHODS_ENV),
+            headers: var(CORS_HEADERS_ENV),
+        }
+    }
+
+    /// 启动日志中的跨域模式: permissive / allowlist / disabled
+    fn mode(&self) -> &'static str {
+        match (self.permissive, &self.origins) {
+            (true, _) => "permissive",
+            (false, Some(_)) => "allowlist",
+            (false, None) => "disabled",
+        }
+    }
+
+    pub(crate) fn layer(&self) -> CorsLayer {
+        if self.permissive {
+            tracing::warn!("{} 已开启，允许任意来源跨域访问，不要用于公开部署", CORS_PERMISSIVE_ENV);
+            return CorsLayer::permissive();
+        }
+        cors_layer(self.origins.as_deref(), self.methods.as_deref(), self.headers.as_deref())
+    }
+}
+
+/// ### 按列表构建跨域配置
+/// #### 参数
+/// - `origins`: 逗号分隔的来源，None 时不允许任何来源
+/// - `methods` / `headers`: 逗号分隔，None 时使用默认值
+/// - 无法解析的项打印警告后忽略
+pub(crate) fn cors_layer(origins: Option<&str>, methods: Option<&str>, headers: Option<&str>) -> CorsLayer {
+    fn split(raw: &str) -> impl Iterator<Item = &str> {
+        raw.split(',').map(str::trim).filter(|s| !s.is_empty())
+    }
+    fn parse<T: std::str::FromStr>(raw: &str, what: &str) -> Vec<T> {
+        split(raw)
+            .filter_map(|item| match item.parse::<T>() {
+                Ok(value) => Some(value),
+                Err(_) => {
+                    tracing::warn!("忽略无效的跨域{} `{}`", what, item);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    let Some(origins) = origins else {
+        return CorsLayer::new();
+    };
+    let layer = CorsLayer::new()
+        .allow_methods(parse::<Method>(&methods.unwrap_or(DEFAULT_CORS_METHODS).to_ascii_uppercase(), "方法"))
+        .allow_headers(parse::<HeaderName>(headers.unwrap_or(DEFAULT_CORS_HEADERS), "请求头"))
+        .expose_headers([
+            HeaderName::from_static("x-request-id"),
+            HeaderName::from_static(crate::coalesce::COALESCED_HEADER),
+            HeaderName::from_static(crate::ALGO_VERSION_HEADER),
+            HeaderName::from_static(crate::POOL_PROXY_HEADER),
+            HeaderName::from_static(crate::idempotency::IDEMPOTENT_REPLAYED_HEADER),
+        ]);
+    if split(origins).any(|origin| origin == "*") {
+        layer.allow_origin(Any)
+    } else {
+        layer.allow_origin(parse::<HeaderValue>(origins, "来源"))
+    }
+}
+
+/// 会话快照文件路径环境变量，未设置时不保存也不恢复会话
+pub(crate) const SESSION_SNAPSHOT_ENV: &str = "GT_SESSION_SNAPSHOT";
+
+/// 会话快照文件路径，也可通过 `--session-snapshot` 参数指定
+fn session_snapshot() -> Option<PathBuf> {
+    std::env::var(SESSION_SNAPSHOT_ENV)
+        .ok()
+        .filter(|s| !s.trim().is_empty())
+        .or_else(|| cli_arg("session-snapshot"))
+        .map(PathBuf::from)
+}
+
+/// 同时进行的阻塞求解数上限环境变量，默认为 CPU 核数 * 4
+pub(crate) const MAX_BLOCKING_SOLVES_ENV: &str = "GT_MAX_BLOCKING_SOLVES";
+/// 等待求解名额的最长时间环境变量（毫秒）
+pub(crate) const SOLVE_PERMIT_TIMEOUT_ENV: &str = "GT_SOLVE_PERMIT_TIMEOUT_MS";
+/// 默认等待求解名额的时间
+pub(crate) const DEFAULT_SOLVE_PERMIT_TIMEOUT_MS: u64 = 200;
+
+/// ### 阻塞求解并发上限: (名额数, 等待名额的超时)
+/// - 避免突发请求占满 tokio 阻塞线程池（默认 512 个线程）
+fn solve_concurrency() -> (usize, Duration) {
+    let cpus = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    (
+        (env_u64(MAX_BLOCKING_SOLVES_ENV, cpus as u64 * 4) as usize).max(1),
+        Duration::from_millis(env_u64(SOLVE_PERMIT_TIMEOUT_ENV, DEFAULT_SOLVE_PERMIT_TIMEOUT_MS)),
+    )
+}
+
+/// 自检时申请点选验证码的地址环境变量
+pub(crate) const SELFTEST_CLICK_URL_ENV: &str = "GT_SELFTEST_CLICK_URL";
+/// 自检时申请滑块验证码的地址环境变量
+pub(crate) const SELFTEST_SLIDE_URL_ENV: &str = "GT_SELFTEST_SLIDE_URL";
+/// 默认的点选申请地址: B 站登录验证码
+const DEFAULT_SELFTEST_CLICK_URL: &str = "https://passport.bilibili.com/x/passport-login/captcha?source=main_web";
+/// 默认的滑块申请地址: 极验官网 demo
+const DEFAULT_SELFTEST_SLIDE_URL: &str = "https://www.geetest.com/demo/gt/register-slide";
+
+/// 自检使用的验证码申请地址: (点选, 滑块)
+pub(crate) fn selftest_urls() -> (String, String) {
+    let var = |name: &str, default: &str| {
+        std::env::var(name)
+            .ok()
+            .filter(|s| !s.trim().is_empty())
+            .unwrap_or_else(|| default.to_string())
+    };
+    (
+        var(SELFTEST_CLICK_URL_ENV, DEFAULT_SELFTEST_CLICK_URL),
+        var(SELFTEST_SLIDE_URL_ENV, DEFAULT_SELFTEST_SLIDE_URL),
+    )
+}
+
+/// 压测并发数环境变量，也可用 `--concurrency` 参数
+pub(crate) const BENCH_CONCURRENCY_ENV: &str = "GT_BENCH_CONCURRENCY";
+/// 压测时长环境变量（秒），也可用 `--duration` 参数
+pub(crate) const BENCH_DURATION_ENV: &str = "GT_BENCH_DURATION_SECS";
+/// 压测的验证码类型环境变量: click 或 slide，也可用 `--kind` 参数
+pub(crate) const BENCH_KIND_ENV: &str = "GT_BENCH_KIND";
+/// 默认 4 个并发求解
+pub(crate) const DEFAULT_BENCH_CONCURRENCY: u64 = 4;
+/// 默认压测 30 秒
+pub(crate) const DEFAULT_BENCH_DURATION_SECS: u64 = 30;
+
+/// ### 压测参数
+pub(crate) struct BenchOptions {
+    pub(crate) kind: VerifyType,
+    pub(crate) concurrency: usize,
+    pub(crate) duration: Duration,
+    /// 验证码申请地址，与自检相同
+    pub(crate) url: String,
+}
+
+/// ### 读取压测参数
+/// - 优先级同监听地址: 环境变量 > `--concurrency` / `--duration` / `--kind` 参数 > 默认值
+/// - 类型默认为滑块；并发数或时长无法解析、为 0，或类型不是 click/slide 时返回错误
+pub(crate) fn bench_options() -> Result<BenchOptions, String> {
+    let value = |env: &str, flag: &str| {
+        std::env::var(env)
+            .ok()
+            .filter(|s| !s.trim().is_empty())
+            .or_else(|| cli_arg(flag))
+    };
+    let positive = |env: &str, flag: &str, default: u64| match value(env, flag) {
+        None => Ok(default),
+        Some(raw) => match raw.trim().parse::<u64>() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(format!("压测参数 {} `{}` 无效，应为正整数", flag, raw)),
+        },
+    };
+    let kind = match value(BENCH_KIND_ENV, "kind").as_deref().map(str::trim) {
+        None | Some("slide") => VerifyType::Slide,
+        Some("click") => VerifyType::Click,
+        Some(other) => return Err(format!("压测参数 kind `{}` 无效，应为 click 或 slide", other)),
+    };
+    let concurrency = positive(BENCH_CONCURRENCY_ENV, "concurrency", DEFAULT_BENCH_CONCURRENCY)?;
+    let duration = positive(BENCH_DURATION_ENV, "duration", DEFAULT_BENCH_DURATION_SECS)?;
+    let (click_url, slide_url) = selftest_urls();
+    Ok(BenchOptions {
+        kind,
+        concurrency: concurrency as usize,
+        duration: Duration::from_secs(duration),
+        url: if kind == VerifyType::Click { click_url } else { slide_url },
+    })
+}
+
+/// tokio 阻塞线程池大小环境变量
+pub(crate) const BLOCKING_THREADS_ENV: &str = "GT_BLOCKING_THREADS";
+/// tokio 默认的阻塞线程池大小
+pub(crate) const DEFAULT_BLOCKING_THREADS: usize = 512;
+
+/// ### tokio 阻塞线程池大小
+/// - 未设置时使用 tokio 默认的 512
+/// - 无法解析或为 0 时返回错误，启动失败
+fn blocking_threads() -> Result<usize, String> {
+    let Some(raw) = std::env::var(BLOCKING_THREADS_ENV).ok().filter(|s| !s.trim().is_empty()) else {
+        return Ok(DEFAULT_BLOCKING_THREADS);
+    };
+    match raw.trim().parse::<usize>() {
+        Ok(threads) if threads > 0 => Ok(threads),
+        _ => Err(format!("{}={} 无效，应为正整数", BLOCKING_THREADS_ENV, raw)),
+    }
+}
+
+/// 极验接口地址环境变量，用于私有部署或测试桩
+pub(crate) const GEETEST_BASE_URL_ENV: &str = "GEETEST_BASE_URL";
+
+/// 极验接口地址，未设置或无法解析时使用 DEFAULT_BASE_URL
+pub(crate) fn geetest_base_url() -> String {
+    let Some(url) = std::env::var(GEETEST_BASE_URL_ENV).ok().filter(|s| !s.trim().is_empty()) else {
+        return DEFAULT_BASE_URL.to_string();
+    };
+    match reqwest::Url::parse(url.trim()) {
+        Ok(_) => url.trim().trim_end_matches('/').to_string(),
+        Err(e) => {
+            tracing::warn!("{} 无效，使用默认地址 {}: {}", GEETEST_BASE_URL_ENV, DEFAULT_BASE_URL, e);
+            DEFAULT_BASE_URL.to_string()
+        }
+    }
+}
+
+/// 极验流量录制目录环境变量
+pub(crate) const RECORD_DIR_ENV: &str = "GT_RECORD_DIR";
+/// 极验流量回放目录环境变量
+pub(crate) const REPLAY_DIR_ENV: &str = "GT_REPLAY_DIR";
+
+/// ### 极验流量录制或回放目录: (录制, 回放)
+/// - 同时设置时以回放为准，避免回放时又把录制写回去
+fn traffic_dirs() -> (Option<PathBuf>, Option<PathBuf>) {
+    let dir = |name: &str| std::env::var(name).ok().filter(|s| !s.trim().is_empty()).map(PathBuf::from);
+    match (dir(RECORD_DIR_ENV), dir(REPLAY_DIR_ENV)) {
+        (record, Some(replay)) => {
+            if record.is_some() {
+                tracing::warn!("同时设置了 {} 与 {}，只回放不录制", RECORD_DIR_ENV, REPLAY_DIR_ENV);
+            }
+            (None, Some(replay))
+        }
+        (record, None) => (record, None),
+    }
+}
+
+/// 管理接口密钥环境变量，未设置时管理接口关闭
+pub(crate) const ADMIN_SECRET_ENV: &str = "GT_ADMIN_SECRET";
+
+/// 管理接口密钥，请求需在 `X-Admin-Secret` 请求头中带上
+fn admin_secret() -> Option<String> {
+    std::env::var(ADMIN_SECRET_ENV).ok().filter(|s| !s.trim().is_empty())
+}
+
+/// simple_match_retry 最多尝试次数环境变量
+pub(crate) const SIMPLE_MATCH_MAX_ATTEMPTS_ENV: &str = "GT_SIMPLE_MATCH_MAX_ATTEMPTS";
+/// 默认最多尝试 10 次，避免持续失败的 challenge 一直刷新下去
+pub(crate) const DEFAULT_SIMPLE_MATCH_MAX_ATTEMPTS: u64 = 10;
+
+/// simple_match_retry 最多尝试次数，至少 1 次，请求中的 max_attempts 可覆盖
+fn simple_match_max_attempts() -> u32 {
+    env_u64(SIMPLE_MATCH_MAX_ATTEMPTS_ENV, DEFAULT_SIMPLE_MATCH_MAX_ATTEMPTS).clamp(1, u32::MAX as u64) as u32
+}
+
+/// 图片下载大小上限环境变量（字节）
+pub(crate) const MAX_IMAGE_BYTES_ENV: &str = "GT_MAX_IMAGE_BYTES";
+
+/// 单张极验图片的下载大小上限，超过时以解析失败中止，默认 4 MiB
+fn max_image_bytes() -> u64 {
+    env_u64(MAX_IMAGE_BYTES_ENV, DEFAULT_MAX_IMAGE_BYTES).max(1)
+}
+
+/// 设为 1 时合并相同的 simple_match 请求
+pub(crate) const COALESCE_ENV: &str = "GT_COALESCE";
+
+/// 是否合并 gt、challenge、session_id 都相同的并发 simple_match 请求，默认关闭
+fn coalesce() -> bool {
+    matches!(std::env::var(COALESCE_ENV).as_deref().map(str::trim), Ok("1" | "true"))
+}
+
+/// 验证结果缓存时间环境变量（毫秒），为 0 或未设置时关闭
+pub(crate) const VERIFY_CACHE_TTL_MS_ENV: &str = "GT_VERIFY_CACHE_TTL_MS";
+/// 验证结果缓存条数上限环境变量
+pub(crate) const VERIFY_CACHE_SIZE_ENV: &str = "GT_VERIFY_CACHE_SIZE";
+/// 默认最多缓存 1024 条验证结果
+pub(crate) const DEFAULT_VERIFY_CACHE_SIZE: u64 = 1024;
+
+/// ### 验证结果缓存: (条数上限, 缓存时间)
+/// - 默认关闭，部分流程要求每次都向极验重新验证
+fn verify_cache() -> Option<(NonZeroUsize, Duration)> {
+    let ttl = env_u64(VERIFY_CACHE_TTL_MS_ENV, 0);
+    let capacity = NonZeroUsize::new(env_u64(VERIFY_CACHE_SIZE_ENV, DEFAULT_VERIFY_CACHE_SIZE) as usize)?;
+    (ttl > 0).then(|| (capacity, Duration::from_millis(ttl)))
+}
+
+/// 幂等键结果保存时间环境变量（秒），为 0 时关闭
+pub(crate) const IDEMPOTENCY_TTL_ENV: &str = "GT_IDEMPOTENCY_TTL_SECS";
+/// 幂等键条数上限环境变量
+pub(crate) const IDEMPOTENCY_SIZE_ENV: &str = "GT_IDEMPOTENCY_SIZE";
+/// 默认保存 10 分钟，覆盖客户端通常的重试间隔
+pub(crate) const DEFAULT_IDEMPOTENCY_TTL_SECS: u64 = 600;
+/// 默认最多保存 4096 个幂等键
+pub(crate) const DEFAULT_IDEMPOTENCY_SIZE: u64 = 4096;
+
+/// ### 幂等键: (条数上限, 保存时间)
+/// - 默认开启，只对带了 `Idempotency-Key` 请求头的验证请求生效
+fn idempotency() -> Option<(NonZeroUsize, Duration)> {
+    let ttl = env_u64(IDEMPOTENCY_TTL_ENV, DEFAULT_IDEMPOTENCY_TTL_SECS);
+    let capacity = NonZeroUsize::new(env_u64(IDEMPOTENCY_SIZE_ENV, DEFAULT_IDEMPOTENCY_SIZE) as usize)?;
+    (ttl > 0).then(|| (capacity, Duration::from_secs(ttl)))
+}
+
+/// 强制代理开关环境变量
+pub(crate) const REQUIRE_PROXY_ENV: &str = "GT_REQUIRE_PROXY";
+
+/// 是否禁止直连极验，默认关闭
+/// - 开启后求解请求必须带代理（或会话绑定了代理），实例的两个客户端都使用代理客户端
+/// - /health/deep 与 /proxy/check 的探测不受影响
+fn require_proxy() -> bool {
+    matches!(std::env::var(REQUIRE_PROXY_ENV).as_deref().map(str::trim), Ok("1" | "true"))
+}
+
+/// 服务端代理池环境变量，逗号分隔；以 `@` 开头时为文件路径，每行一个代理
+pub(crate) const PROXY_POOL_ENV: &str = "GT_PROXY_POOL";
+
+/// ### 服务端代理池
+/// - 请求设置 use_pool 时从这里取代理，调用方不必各自维护代理列表
+/// - 文件中的空行和 `#` 开头的行忽略
+/// - 地址无效的代理记录警告后跳过
+fn proxy_pool() -> Vec<ProxyConfig> {
+    let Some(raw) = std::env::var(PROXY_POOL_ENV).ok().filter(|s| !s.trim().is_empty()) else {
+        return Vec::new();
+    };
+    let raw = match raw.trim().strip_prefix('@') {
+        Some(path) => match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => {
+                tracing::warn!("读取 {} 指定的代理池文件 {} 失败: {}", PROXY_POOL_ENV, path, e);
+                return Vec::new();
+            }
+        },
+        None => raw.replace(',', "\n"),
+    };
+    raw.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let proxy = ProxyConfig::from(line.to_string());
+            match proxy.resolved_url() {
+                Ok(_) => Some(proxy),
+                Err(e) => {
+                    tracing::warn!(
+                        "{} 中的 `{}` 不是有效的代理，已忽略: {}",
+                        PROXY_POOL_ENV,
+                        redact::proxy_url(line),
+                        e
+                    );
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// 代理池换代理重试次数环境变量
+pub(crate) const PROXY_POOL_RETRIES_ENV: &str = "GT_PROXY_POOL_RETRIES";
+/// 默认失败后再换 2 个代理，即每个请求最多尝试 3 个
+pub(crate) const DEFAULT_PROXY_POOL_RETRIES: u64 = 2;
+
+/// ### 代理池换代理重试次数
+/// - 代理不可用、连接失败或超时时换下一个代理，每个请求最多尝试该值 + 1 个代理
+/// - 服务端代理池与请求中的 proxy_pool 都受此限制，所有使用代理池的接口相同
+fn proxy_pool_retries() -> usize {
+    env_u64(PROXY_POOL_RETRIES_ENV, DEFAULT_PROXY_POOL_RETRIES).min(u32::MAX as u64) as usize
+}
+
+/// JSON Lines 访问日志文件路径环境变量，未设置时不写访问日志
+pub(crate) const ACCESS_LOG_PATH_ENV: &str = "ACCESS_LOG_PATH";
+/// 访问日志轮转大小环境变量（字节），为 0 时不轮转
+pub(crate) const ACCESS_LOG_MAX_BYTES_ENV: &str = "ACCESS_LOG_MAX_BYTES";
+/// 默认访问日志轮转大小: 100 MiB
+pub(crate) const DEFAULT_ACCESS_LOG_MAX_BYTES: u64 = 100 * 1024 * 1024;
+
+/// 访问日志: (文件路径, 轮转大小)
+fn access_log() -> Option<(PathBuf, u64)> {
+    let path = std::env::var(ACCESS_LOG_PATH_ENV).ok().filter(|s| !s.trim().is_empty())?;
+    Some((
+        PathBuf::from(path),
+        env_u64(ACCESS_LOG_MAX_BYTES_ENV, DEFAULT_ACCESS_LOG_MAX_BYTES),
+    ))
+}
+
+/// ### 启动时确定的全部配置
+/// - 由 `Config::load` 从环境变量与命令行参数一次读齐，之后各处只读这里的值
+/// - `/admin/reload` 会重新读取客户端参数，不经过这里
+pub(crate) struct Config {
+    pub(crate) bind: BindAddr,
+    pub(crate) tls: Option<(PathBuf, PathBuf)>,
+    pub(crate) shutdown_grace: Duration,
+    pub(crate) blocking_threads: usize,
+    pub(crate) session_ttl: Duration,
+    pub(crate) session_snapshot: Option<PathBuf>,
+    pub(crate) client: ClientOptions,
+    /// (探测地址, 超时, 缓存时间)
+    pub(crate) health_probe: (String, Duration, Duration),
+    pub(crate) proxy_check_url: String,
+    /// (每秒请求数, 突发请求数)
+    pub(crate) rate_limit: (u64, u64),
+    /// (失败率阈值, 统计窗口, 最少样本数)，未开启时为 None
+    pub(crate) liveness: Option<(f64, Duration, usize)>,
+    pub(crate) request_deadline: Option<Duration>,
+    pub(crate) max_body_bytes: usize,
+    pub(crate) compression_min_bytes: u16,
+    pub(crate) cors: CorsConfig,
+    /// (名额数, 等待名额的超时)
+    pub(crate) solve_concurrency: (usize, Duration),
+    pub(crate) geetest_base_url: String,
+    pub(crate) simple_match_max_attempts: u32,
+    pub(crate) max_image_bytes: u64,
+    pub(crate) record_dir: Option<PathBuf>,
+    pub(crate) replay_dir: Option<PathBuf>,
+    pub(crate) admin_secret: Option<String>,
+    pub(crate) coalesce: bool,
+    /// (条数上限, 缓存时间)，未开启时为 None
+    pub(crate) verify_cache: Option<(NonZeroUsize, Duration)>,
+    /// (条数上限, 保存时间)，关闭时为 None
+    pub(crate) idempotency: Option<(NonZeroUsize, Duration)>,
+    pub(crate) require_proxy: bool,
+    pub(crate) proxy_pool: Vec<ProxyConfig>,
+    pub(crate) proxy_pool_retries: usize,
+    /// (文件路径, 轮转大小)
+    pub(crate) access_log: Option<(PathBuf, u64)>,
+}
+
+impl Config {
+    /// ### 读取全部配置
+    /// #### 返回值
+    /// - 监听地址、TLS、阻塞线程数无效时返回错误，启动失败；其余项无效时打印警告并使用默认值
+    pub(crate) fn load() -> Result<Self, String> {
+        let (record_dir, replay_dir) = traffic_dirs();
+        Ok(Self {
+            bind: resolve_bind()?,
+            tls: tls_paths()?,
+            shutdown_grace: shutdown_grace(),
+            blocking_threads: blocking_threads()?,
+            session_ttl: session_ttl(),
+            session_snapshot: session_snapshot(),
+            client: client_options(),
+            health_probe: health_probe(),
+            proxy_check_url: proxy_check_url(),
+            rate_limit: rate_limit(),
+            liveness: liveness(),
+            request_deadline: request_deadline(),
+            max_body_bytes: max_body_bytes(),
+            compression_min_bytes: compression_min_bytes(),
+            cors: CorsConfig::from_env(),
+            solve_concurrency: solve_concurrency(),
+            geetest_base_url: geetest_base_url(),
+            simple_match_max_attempts: simple_match_max_attempts(),
+            max_image_bytes: max_image_bytes(),
+            record_dir,
+            replay_dir,
+            admin_secret: admin_secret(),
+            coalesce: coalesce(),
+            verify_cache: verify_cache(),
+            idempotency: idempotency(),
+            require_proxy: require_proxy(),
+            proxy_pool: proxy_pool(),
+            proxy_pool_retries: proxy_pool_retries(),
+            access_log: access_log(),
+        })
+    }
+
+    /// 极验流量录制或回放，都未设置时直接访问网络
+    pub(crate) fn traffic(&self) -> Option<Traffic> {
+        if let Some(replay) = &self.replay_dir {
+            tracing::info!("从 {} 回放极验流量，不再访问极验", replay.display());
+            return Some(Traffic::replayer(replay.clone()));
+        }
+        let record = self.record_dir.as_ref()?;
+        tracing::info!("录制极验流量到 {}", record.display());
+        Some(Traffic::recorder(record.clone()))
+    }
+
+    /// ### 生效配置的 JSON 摘要
+    /// - 启动时整行打印，便于在部署日志中留存并对比不同版本
+    /// - 不包含管理接口密钥，只记录是否开启
+    pub(crate) fn summary(&self) -> serde_json::Value {
+        let ms = |d: Duration| d.as_millis() as u64;
+        let path = |p: &Option<PathBuf>| p.as_ref().map(|p| p.display().to_string());
+        let (probe_url, probe_timeout, probe_cache) = &self.health_probe;
+        let (max_solves, solve_permit_timeout) = self.solve_concurrency;
+        serde_json::json!({
+            "bind": self.bind.to_string(),
+            "tls": self.tls.is_some(),
+            "shutdown_grace_ms": ms(self.shutdown_grace),
+            "blocking_threads": self.blocking_threads,
+            "session_ttl_ms": ms(self.session_ttl),
+            "session_snapshot": path(&self.session_snapshot),
+            "client": {
+                "connect_timeout_ms": ms(self.client.connect_timeout),
+                "request_timeout_ms": ms(self.client.request_timeout),
+                "max_clients": self.client.max_clients.get(),
+                "pool_max_idle_per_host": self.client.pool_max_idle_per_host,
+                "pool_idle_timeout_ms": ms(self.client.pool_idle_timeout),
+                "breaker_threshold": self.client.breaker_threshold,
+                "breaker_cooldown_ms": ms(self.client.breaker_cooldown),
+                "build_retries": self.client.build_retry.max_retries,
+                "build_retry_delay_ms": ms(self.client.build_retry.base_delay),
+                "dns_overrides": self.client.dns_overrides.iter().map(|(host, ip)| format!("{}={}", host, ip)).collect::<Vec<_>>(),
+            },
+            "health_probe": {
+                "url": probe_url,
+                "timeout_ms": ms(*probe_timeout),
+                "cache_ms": ms(*probe_cache),
+            },
+            "proxy_check_url": self.proxy_check_url,
+            "rate_limit": { "rps": self.rate_limit.0, "burst": self.rate_limit.1 },
+            "liveness": self.liveness.map(|(rate, window, min_samples)| serde_json::json!({
+                "failure_rate": rate,
+                "window_ms": ms(window),
+                "min_samples": min_samples,
+            })),
+            "request_deadline_ms": self.request_deadline.map(ms),
+            "max_body_bytes": self.max_body_bytes,
+            "compression_min_bytes": self.compression_min_bytes,
+            "cors": {
+                "mode": self.cors.mode(),
+                "origins": self.cors.origins,
+                "methods": self.cors.methods.as_deref().unwrap_or(DEFAULT_CORS_METHODS),
+                "headers": self.cors.headers.as_deref().unwrap_or(DEFAULT_CORS_HEADERS),
+            },
+            "max_blocking_solves": max_solves,
+            "solve_permit_timeout_ms": ms(solve_permit_timeout),
+            "geetest_base_url": self.geetest_base_url,
+            "simple_match_max_attempts": self.simple_match_max_attempts,
+            "max_image_bytes": self.max_image_bytes,
+            "record_dir": path(&self.record_dir),
+            "replay_dir": path(&self.replay_dir),
+            "admin_enabled": self.admin_secret.is_some(),
+            "coalesce": self.coalesce,
+            "verify_cache": self.verify_cache.map(|(capacity, ttl)| serde_json::json!({
+                "size": capacity.get(),
+                "ttl_ms": ms(ttl),
+            })),
+            "idempotency": self.idempotency.map(|(capacity, ttl)| serde_json::json!({
+                "size": capacity.get(),
+                "ttl_ms": ms(ttl),
+            })),
+            "require_proxy": self.require_proxy,
+            "proxy_pool": self.proxy_pool.iter().map(session::proxy_label).collect::<Vec<_>>(),
+            "proxy_pool_retries": self.proxy_pool_retries,
+            "access_log": self.access_log.as_ref().map(|(path, max_bytes)| {
+                serde_json::json!({ "path": path.display().to_string(), "max_bytes": max_bytes })
+            }),
+        })
+    }
+}
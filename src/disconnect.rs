@@ -0,0 +1,80 @@
+// disconnect.rs
+
+use crate::metrics::Metrics;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// ### 客户端断开检测
+/// - 客户端断开时 hyper 会丢弃处理函数的 future，guard 随之被 drop
+/// - 未调用 `finish` 就被 drop 即视为客户端已断开: 设置取消标记、记录日志和指标
+/// - spawn_blocking 中的求解无法中断，只能在开始前和结束后检查取消标记，
+///   开始前已取消则不再求解，结束后已取消则丢弃结果，不更新熔断等状态
+pub(crate) struct DisconnectGuard {
+    route: &'static str,
+    started: Instant,
+    metrics: Arc<Metrics>,
+    cancelled: Arc<AtomicBool>,
+    finished: bool,
+}
+
+/// 传入阻塞任务的取消标记
+#[derive(Clone)]
+pub(crate) struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl DisconnectGuard {
+    pub(crate) fn new(route: &'static str, started: Instant, metrics: Arc<Metrics>) -> Self {
+        Self { route, started, metrics, cancelled: Arc::new(AtomicBool::new(false)), finished: false }
+    }
+
+    pub(crate) fn token(&self) -> CancelToken {
+        CancelToken(Arc::clone(&self.cancelled))
+    }
+
+    /// 响应已生成，之后的 drop 不再视为断开
+    pub(crate) fn finish(mut self) {
+        self.finished = true;
+    }
+}
+
+impl Drop for DisconnectGuard {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        self.cancelled.store(true, Ordering::Relaxed);
+        tracing::warn!(
+            route = self.route,
+            latency_ms = self.started.elapsed().as_millis() as u64,
+            "客户端已断开，放弃求解"
+        );
+        self.metrics.record_cancelled(self.route);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dropping_before_finish_cancels() {
+        let metrics = Arc::new(Metrics::new());
+        let guard = DisconnectGuard::new("/click/test", Instant::now(), Arc::clone(&metrics));
+        let token = guard.token();
+        drop(guard);
+        assert!(token.is_cancelled());
+        assert!(metrics.render(&[]).contains("result=\"cancelled\"} 1"));
+
+        let guard = DisconnectGuard::new("/click/test", Instant::now(), Arc::clone(&metrics));
+        let token = guard.token();
+        guard.finish();
+        assert!(!token.is_cancelled());
+        assert!(metrics.render(&[]).contains("result=\"cancelled\"} 1"));
+    }
+}
@@ -1,5 +1,8 @@
+use schemars::JsonSchema;
+use serde::Serialize;
 use std::error::Error as StdError;
 use std::fmt::{Debug, Display, Formatter};
+use std::time::Duration;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -7,7 +10,7 @@ pub struct Error {
     inner: Box<Inner>,
 }
 
-pub(crate) type BoxError = Box<dyn StdError + Send + Sync>;
+pub type BoxError = Box<dyn StdError + Send + Sync>;
 
 /// ### 错误内容
 /// - kind: 错误类型
@@ -20,6 +23,21 @@ struct Inner {
 #[derive(Debug)]
 pub(crate) enum Kind {
     NetWorkError,
+    Timeout,
+    InvalidProxy,
+    /// 极验返回了非 2xx 状态，body 为截断后的响应体
+    Upstream { status: u16, body: String },
+    /// 极验正常响应但拒绝了请求，例如验证未通过、challenge 已过期
+    Rejected(String),
+    /// 极验封禁了出口 IP，retry_after 为极验给出的解封等待时间
+    Banned { retry_after: Option<Duration> },
+    /// 极验返回 429，retry_after 为 Retry-After 响应头给出的等待时间
+    RateLimited { retry_after: Option<Duration> },
+    CircuitOpen(String),
+    /// 重试次数用完，source 为最后一次的错误
+    Exhausted { attempts: u32 },
+    InvalidInput(String),
+    Unsupported(String),
     MissingParam(String),
     ParseError,
     Other(String),
@@ -32,6 +50,16 @@ impl Debug for Error {
         builder.field("错误类型", &self.inner.kind);
         match &self.inner.kind {
             Kind::NetWorkError => {}
+            Kind::Timeout => {}
+            Kind::InvalidProxy => {}
+            Kind::Upstream { status, body } => {builder.field("状态码", status).field("响应", body);}
+            Kind::Rejected(s) => {builder.field("信息", s);}
+            Kind::Banned { retry_after } => {builder.field("等待", retry_after);}
+            Kind::RateLimited { retry_after } => {builder.field("等待", retry_after);}
+            Kind::CircuitOpen(s) => {builder.field("信息", s);}
+            Kind::Exhausted { attempts } => {builder.field("尝试次数", attempts);}
+            Kind::InvalidInput(s) => {builder.field("信息", s);}
+            Kind::Unsupported(s) => {builder.field("信息", s);}
             Kind::MissingParam(s) => {builder.field("信息", s);}
             Kind::ParseError => {}
             Kind::Other(s) => {builder.field("信息", s);}
@@ -74,24 +102,238 @@ impl Error {
             inner: Box::new(Inner { kind, source: None }),
         }
     }
+
+    /// 是否为可重试的临时错误（网络层或上游超时）
+    pub fn is_transient(&self) -> bool {
+        matches!(self.inner.kind, Kind::NetWorkError | Kind::Timeout)
+    }
+
+    /// 是否为极验封禁出口 IP
+    pub fn is_banned(&self) -> bool {
+        matches!(self.inner.kind, Kind::Banned { .. })
+    }
+
+    /// 是否为极验返回 429 限流
+    pub fn is_rate_limited(&self) -> bool {
+        matches!(self.inner.kind, Kind::RateLimited { .. })
+    }
+
+    /// 极验给出的重试等待时间，封禁或限流时可能提供
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self.inner.kind {
+            Kind::Banned { retry_after } | Kind::RateLimited { retry_after } => retry_after,
+            _ => None,
+        }
+    }
+
+    /// 对外暴露的错误码
+    pub fn code(&self) -> ErrorCode {
+        match self.inner.kind {
+            Kind::NetWorkError => ErrorCode::UpstreamHttp,
+            Kind::Timeout => ErrorCode::UpstreamTimeout,
+            Kind::InvalidProxy => ErrorCode::InvalidProxy,
+            Kind::Upstream { .. } => ErrorCode::UpstreamStatus,
+            Kind::Rejected(_) => ErrorCode::Rejected,
+            Kind::Banned { .. } => ErrorCode::IpBanned,
+            Kind::RateLimited { .. } => ErrorCode::UpstreamRateLimited,
+            Kind::CircuitOpen(_) => ErrorCode::CircuitOpen,
+            Kind::Exhausted { .. } => ErrorCode::RetriesExhausted,
+            Kind::InvalidInput(_) => ErrorCode::InvalidInput,
+            Kind::Unsupported(_) => ErrorCode::Unsupported,
+            Kind::MissingParam(_) => ErrorCode::MissingParam,
+            Kind::ParseError => ErrorCode::ParseFailed,
+            Kind::Other(_) => ErrorCode::Other,
+        }
+    }
+}
+
+/// ### 错误码
+/// - 序列化为稳定的字符串，供客户端按类别判断是否重试
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// 代理地址无效
+    InvalidProxy,
+    /// 开启 GT_REQUIRE_PROXY 时请求未指定代理
+    ProxyRequired,
+    /// 代理连续失败已熔断，冷却结束前直接拒绝
+    CircuitOpen,
+    /// 请求极验失败（网络层）
+    UpstreamHttp,
+    /// 请求极验超时
+    UpstreamTimeout,
+    /// 极验返回了非 2xx 状态
+    UpstreamStatus,
+    /// 极验拒绝了请求，例如验证未通过，重试同一参数无意义
+    Rejected,
+    /// simple_match_retry 用完了尝试次数
+    RetriesExhausted,
+    /// 极验封禁了出口 IP，应更换代理或等待解封
+    IpBanned,
+    /// 极验返回 429，应按 Retry-After 等待后重试
+    UpstreamRateLimited,
+    /// 极验响应解析失败
+    ParseFailed,
+    /// 极验响应缺少字段
+    MissingParam,
+    /// 服务内部错误
+    Internal,
+    /// 请求过于频繁，被限流
+    RateLimited,
+    /// 请求体格式错误或缺少 Content-Type
+    BadRequest,
+    /// gt、challenge 等参数格式不对，未发往极验
+    InvalidInput,
+    /// 能识别但尚未实现求解的验证码类型
+    Unsupported,
+    /// 同时进行的求解过多，暂时无法处理
+    Overloaded,
+    /// 请求处理超过了 GT_REQUEST_DEADLINE_MS
+    DeadlineExceeded,
+    /// 管理接口缺少或带错了密钥
+    Unauthorized,
+    /// 其他错误
+    Other,
+}
+
+impl ErrorCode {
+    /// ### 错误码对照表: (字符串形式, 数字形式)
+    /// - 两种形式都只在这里定义，新增错误码时一并分配数字，已分配的数字不再改动
+    /// - 数字按类别分段:
+    ///
+    /// | 数字 | 字符串 | 说明 |
+    /// | ---- | ------ | ---- |
+    /// | 1001 | invalid_proxy | 代理地址无效 |
+    /// | 1002 | proxy_required | 开启 GT_REQUIRE_PROXY 时未指定代理 |
+    /// | 1003 | circuit_open | 代理已熔断 |
+    /// | 2001 | upstream_http | 请求极验失败（网络层） |
+    /// | 2002 | upstream_timeout | 请求极验超时 |
+    /// | 2003 | upstream_status | 极验返回非 2xx |
+    /// | 2004 | rejected | 极验拒绝了请求 |
+    /// | 2005 | ip_banned | 出口 IP 被封禁 |
+    /// | 2006 | parse_failed | 极验响应解析失败 |
+    /// | 2007 | missing_param | 极验响应缺少字段 |
+    /// | 2008 | retries_exhausted | simple_match_retry 用完尝试次数 |
+    /// | 2009 | upstream_rate_limited | 极验返回 429 |
+    /// | 3001 | bad_request | 请求体格式错误 |
+    /// | 3002 | invalid_input | gt、challenge 等参数格式不对 |
+    /// | 3003 | unsupported | 尚未支持的验证码类型 |
+    /// | 3004 | unauthorized | 管理接口密钥错误 |
+    /// | 4001 | rate_limited | 被限流 |
+    /// | 4002 | overloaded | 同时进行的求解过多 |
+    /// | 4003 | deadline_exceeded | 超过 GT_REQUEST_DEADLINE_MS |
+    /// | 4004 | internal | 服务内部错误 |
+    /// | 9999 | other | 其他错误 |
+    fn entry(self) -> (&'static str, u16) {
+        match self {
+            ErrorCode::InvalidProxy => ("invalid_proxy", 1001),
+            ErrorCode::ProxyRequired => ("proxy_required", 1002),
+            ErrorCode::CircuitOpen => ("circuit_open", 1003),
+            ErrorCode::UpstreamHttp => ("upstream_http", 2001),
+            ErrorCode::UpstreamTimeout => ("upstream_timeout", 2002),
+            ErrorCode::UpstreamStatus => ("upstream_status", 2003),
+            ErrorCode::Rejected => ("rejected", 2004),
+            ErrorCode::IpBanned => ("ip_banned", 2005),
+            ErrorCode::ParseFailed => ("parse_failed", 2006),
+            ErrorCode::MissingParam => ("missing_param", 2007),
+            ErrorCode::RetriesExhausted => ("retries_exhausted", 2008),
+            ErrorCode::UpstreamRateLimited => ("upstream_rate_limited", 2009),
+            ErrorCode::BadRequest => ("bad_request", 3001),
+            ErrorCode::InvalidInput => ("invalid_input", 3002),
+            ErrorCode::Unsupported => ("unsupported", 3003),
+            ErrorCode::Unauthorized => ("unauthorized", 3004),
+            ErrorCode::RateLimited => ("rate_limited", 4001),
+            ErrorCode::Overloaded => ("overloaded", 4002),
+            ErrorCode::DeadlineExceeded => ("deadline_exceeded", 4003),
+            ErrorCode::Internal => ("internal", 4004),
+            ErrorCode::Other => ("other", 9999),
+        }
+    }
+
+    /// 与序列化结果一致的字符串形式
+    pub fn as_str(self) -> &'static str {
+        self.entry().0
+    }
+
+    /// 稳定的数字形式，供按整数判断错误类别的客户端使用
+    pub fn number(self) -> u16 {
+        self.entry().1
+    }
 }
 
-pub(crate) fn net_work_error<E: Into<BoxError>>(e: E) -> Error {
-    Error::new(Kind::NetWorkError, Some(e))
+/// 网络错误，超时会单独归类为 `Kind::Timeout`
+pub fn net_work_error<E: Into<BoxError>>(e: E) -> Error {
+    let e: BoxError = e.into();
+    let timeout = e
+        .downcast_ref::<reqwest::Error>()
+        .is_some_and(|e| e.is_timeout());
+    let kind = if timeout { Kind::Timeout } else { Kind::NetWorkError };
+    Error::new(kind, Some(e))
 }
 
-pub(crate) fn missing_param(s: &str) -> Error {
+pub fn invalid_proxy<E: Into<BoxError>>(e: E) -> Error {
+    Error::new(Kind::InvalidProxy, Some(e))
+}
+
+/// 错误中保留的极验响应体长度上限（字符）
+const UPSTREAM_BODY_LIMIT: usize = 256;
+
+/// 极验返回了非 2xx 状态，响应体截断后保留
+pub fn upstream_status(status: u16, body: &str) -> Error {
+    let body = body.chars().take(UPSTREAM_BODY_LIMIT).collect();
+    Error::new_without_source(Kind::Upstream { status, body })
+}
+
+/// 极验拒绝了请求
+pub fn rejected(s: &str) -> Error {
+    Error::new_without_source(Kind::Rejected(s.to_string()))
+}
+
+/// 极验封禁了出口 IP
+pub fn banned(retry_after: Option<Duration>) -> Error {
+    Error::new_without_source(Kind::Banned { retry_after })
+}
+
+/// 极验返回 429 限流
+pub fn rate_limited(retry_after: Option<Duration>) -> Error {
+    Error::new_without_source(Kind::RateLimited { retry_after })
+}
+
+/// 重试次数用完，保留最后一次的错误作为 source
+pub fn exhausted(attempts: u32, last: Error) -> Error {
+    Error::new(Kind::Exhausted { attempts }, Some(last))
+}
+
+pub fn circuit_open(s: &str) -> Error {
+    Error::new_without_source(Kind::CircuitOpen(s.to_string()))
+}
+
+pub fn invalid_input(s: &str) -> Error {
+    Error::new_without_source(Kind::InvalidInput(s.to_string()))
+}
+
+pub fn unsupported(s: &str) -> Error {
+    Error::new_without_source(Kind::Unsupported(s.to_string()))
+}
+
+pub fn missing_param(s: &str) -> Error {
     Error::new_without_source(Kind::MissingParam(s.to_string()))
 }
 
-pub(crate) fn parse_error<E: Into<BoxError>>(e: E) -> Error {
+pub fn parse_error<E: Into<BoxError>>(e: E) -> Error {
     Error::new(Kind::ParseError, Some(e))
 }
 
-pub(crate) fn other<E: Into<BoxError>>(s: &str, e: E) -> Error {
+/// 极验响应不符合预期格式，带上截断后的响应片段便于排查
+pub fn decode_error(reason: impl Display, body: &str) -> Error {
+    let snippet = body.chars().take(UPSTREAM_BODY_LIMIT).collect::<String>();
+    parse_error(format!("{}，响应片段: {}", reason, snippet))
+}
+
+pub fn other<E: Into<BoxError>>(s: &str, e: E) -> Error {
     Error::new(Kind::Other(s.to_string()), Some(e))
 }
 
-pub(crate) fn other_without_source(s: &str) -> Error {
+pub fn other_without_source(s: &str) -> Error {
     Error::new_without_source(Kind::Other(s.to_string()))
 }
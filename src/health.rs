@@ -0,0 +1,91 @@
+// health.rs
+
+use reqwest::blocking::Client;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// ### 上游探测结果
+#[derive(Clone, Serialize, JsonSchema)]
+pub(crate) struct ProbeResult {
+    pub(crate) reachable: bool,
+    pub(crate) url: String,
+    pub(crate) latency_ms: u64,
+    pub(crate) status: Option<u16>,
+    pub(crate) error: Option<String>,
+    /// 结果是否来自缓存
+    pub(crate) cached: bool,
+}
+
+/// ### 深度健康检查
+/// - 通过不带代理的默认客户端对极验发起 HEAD 请求
+/// - 结果缓存 cache_ttl，避免负载均衡器频繁探测时打满上游
+pub(crate) struct DeepHealth {
+    url: String,
+    timeout: Duration,
+    cache_ttl: Duration,
+    last: Mutex<Option<(Instant, ProbeResult)>>,
+}
+
+impl DeepHealth {
+    pub(crate) fn new(url: String, timeout: Duration, cache_ttl: Duration) -> Self {
+        Self {
+            url,
+            timeout,
+            cache_ttl,
+            last: Mutex::new(None),
+        }
+    }
+
+    /// 探测地址，/warmup 也用它预热连接
+    pub(crate) fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub(crate) fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn cached(&self) -> Option<ProbeResult> {
+        let last = self.last.lock().ok()?;
+        let (at, result) = last.as_ref()?;
+        (at.elapsed() < self.cache_ttl).then(|| ProbeResult {
+            cached: true,
+            ..result.clone()
+        })
+    }
+
+    /// ### 执行探测
+    /// - 阻塞客户端的请求放到 spawn_blocking 中执行
+    pub(crate) async fn probe(&self, client: Arc<Client>) -> ProbeResult {
+        if let Some(result) = self.cached() {
+            return result;
+        }
+
+        let url = self.url.clone();
+        let timeout = self.timeout;
+        let started = Instant::now();
+        let outcome = tokio::task::spawn_blocking(move || client.head(&url).timeout(timeout).send()).await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+
+        let (reachable, status, error) = match outcome {
+            // 能拿到任意 HTTP 响应即视为可达，极验对 HEAD 可能返回 4xx
+            Ok(Ok(res)) => (!res.status().is_server_error(), Some(res.status().as_u16()), None),
+            Ok(Err(e)) => (false, None, Some(e.to_string())),
+            Err(e) => (false, None, Some(e.to_string())),
+        };
+        let result = ProbeResult {
+            reachable,
+            url: self.url.clone(),
+            latency_ms,
+            status,
+            error,
+            cached: false,
+        };
+        if let Ok(mut last) = self.last.lock() {
+            *last = Some((Instant::now(), result.clone()));
+        }
+        result
+    }
+}
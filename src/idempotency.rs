@@ -0,0 +1,162 @@
+// idempotency.rs
+
+use crate::coalesce::Shared;
+use crate::sync::lock_or_recover;
+use axum::{
+    http::{HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use lru::LruCache;
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+
+/// 客户端带上的幂等键请求头
+pub(crate) const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+/// 返回已保存的结果时带上的响应头，值为 `true`
+pub(crate) const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+/// 幂等键长度上限
+pub(crate) const MAX_KEY_LEN: usize = 255;
+
+/// 同一个幂等键被用于参数不同的请求
+pub(crate) struct KeyReused;
+
+/// ### 幂等键
+/// - 客户端重发带有相同 `Idempotency-Key` 的验证时返回第一次的结果，不再验证，避免只能使用一次的 challenge 被重复提交
+/// - 第一次仍在进行时，重发的请求等待其结果；它的客户端断开时由等待中的下一个请求接着执行
+/// - 结果保存一段时间后丢弃，条数有上限，超出后淘汰最久未使用的
+/// - 没有真正执行的响应（限流 429、繁忙 503）不保存，客户端可以用同一个键重试
+pub(crate) struct IdempotencyStore {
+    ttl: Duration,
+    entries: Mutex<LruCache<String, Entry>>,
+}
+
+struct Entry {
+    /// 请求参数的指纹，用于发现同一个键被用于不同的请求
+    fingerprint: String,
+    created: Instant,
+    response: Arc<OnceCell<Shared>>,
+}
+
+impl IdempotencyStore {
+    pub(crate) fn new(capacity: NonZeroUsize, ttl: Duration) -> Self {
+        Self { ttl, entries: Mutex::new(LruCache::new(capacity)) }
+    }
+
+    /// ### 执行请求或返回已保存的结果
+    /// #### 参数
+    /// - `key`: 请求头中的幂等键
+    /// - `fingerprint`: 请求参数的指纹，相同的键必须对应相同的指纹
+    /// - `execute`: 完整的处理过程，只有第一次才会执行
+    /// #### 返回值
+    /// - 处理的响应，复用得到的响应带有 `idempotent-replayed: true`
+    /// - 键已用于指纹不同的请求时返回 `KeyReused`
+    pub(crate) async fn run<F>(&self, key: String, fingerprint: String, execute: F) -> Result<Response, KeyReused>
+    where
+        F: Future<Output = Response>,
+    {
+        let cell = {
+            let mut entries = lock_or_recover(&self.entries, "幂等键");
+            let expired = entries
+                .peek(&key)
+                .is_some_and(|entry| entry.response.initialized() && entry.created.elapsed() >= self.ttl);
+            if expired {
+                entries.pop(&key);
+            }
+            match entries.get(&key) {
+                Some(entry) if entry.fingerprint != fingerprint => return Err(KeyReused),
+                Some(entry) => Arc::clone(&entry.response),
+                None => {
+                    let cell = Arc::new(OnceCell::new());
+                    let entry = Entry { fingerprint, created: Instant::now(), response: Arc::clone(&cell) };
+                    entries.put(key.clone(), entry);
+                    cell
+                }
+            }
+        };
+        let mut executed = false;
+        let shared = cell
+            .get_or_init(|| async {
+                executed = true;
+                Shared::buffer(execute.await).await
+            })
+            .await
+            .clone();
+        if matches!(shared.status(), StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE) {
+            let mut entries = lock_or_recover(&self.entries, "幂等键");
+            if entries.peek(&key).is_some_and(|entry| Arc::ptr_eq(&entry.response, &cell)) {
+                entries.pop(&key);
+            }
+        }
+        let mut response = shared.into_response();
+        if !executed {
+            tracing::info!("幂等键已处理过，返回保存的结果");
+            response.headers_mut().insert(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn store(ttl: Duration) -> IdempotencyStore {
+        IdempotencyStore::new(NonZeroUsize::new(8).unwrap(), ttl)
+    }
+
+    #[tokio::test]
+    async fn repeated_key_returns_the_first_result() {
+        let store = Arc::new(store(Duration::from_millis(100)));
+        let runs = Arc::new(AtomicUsize::new(0));
+        let execute = |status: StatusCode| {
+            let runs = Arc::clone(&runs);
+            async move {
+                runs.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                (status, "validate").into_response()
+            }
+        };
+
+        // 第一次仍在进行时重发，等待其结果
+        let first = tokio::spawn({
+            let store = Arc::clone(&store);
+            let execute = execute(StatusCode::OK);
+            async move { store.run("k".to_string(), "a".to_string(), execute).await.ok().unwrap() }
+        });
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let retried = store.run("k".to_string(), "a".to_string(), execute(StatusCode::OK)).await.ok().unwrap();
+        assert!(retried.headers().contains_key(IDEMPOTENT_REPLAYED_HEADER));
+        assert!(!first.await.unwrap().headers().contains_key(IDEMPOTENT_REPLAYED_HEADER));
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        // 同一个键用于其他请求
+        assert!(store.run("k".to_string(), "b".to_string(), execute(StatusCode::OK)).await.is_err());
+
+        // 过期后重新执行
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        store.run("k".to_string(), "b".to_string(), execute(StatusCode::OK)).await.ok().unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn overloaded_responses_are_not_kept() {
+        let store = store(Duration::from_secs(60));
+        let busy = store
+            .run("k".to_string(), "a".to_string(), async { StatusCode::SERVICE_UNAVAILABLE.into_response() })
+            .await
+            .ok()
+            .unwrap();
+        assert_eq!(busy.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let retried = store
+            .run("k".to_string(), "a".to_string(), async { StatusCode::OK.into_response() })
+            .await
+            .ok()
+            .unwrap();
+        assert_eq!(retried.status(), StatusCode::OK);
+        assert!(!retried.headers().contains_key(IDEMPOTENT_REPLAYED_HEADER));
+    }
+}
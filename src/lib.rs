@@ -0,0 +1,42 @@
+// lib.rs
+
+//! ### 极验验证码求解库
+//! - 点选 `Click` 与滑块 `Slide` 实现了 `Api` / `GenerateW` / `Test`，可不启动 HTTP 服务直接调用
+//! - `ClientManager` 按代理、User-Agent 等参数构建并缓存上游客户端
+//!
+//! ```no_run
+//! use biliticker_gt::{Click, ClientManager, ClientOptions, ClientSpec};
+//!
+//! fn main() -> biliticker_gt::Result<()> {
+//!     let (gt, challenge) = ("<gt>", "<challenge>");
+//!     let manager = ClientManager::new(ClientOptions::default());
+//!     let client = manager.get(&ClientSpec::default())?;
+//!     let mut click = Click::new(client.clone(), client);
+//!     let validate = click.simple_match(gt, challenge)?;
+//!     println!("{}", validate);
+//!     Ok(())
+//! }
+//! ```
+
+pub mod abstraction;
+pub mod breaker;
+pub mod click;
+pub mod client;
+pub mod error;
+pub mod retry;
+pub mod slide;
+pub mod sync;
+pub mod timing;
+pub mod traffic;
+pub mod w;
+
+pub use crate::abstraction::{Api, CapturedImage, GenerateW, Test, VerifyPayload, VerifyType};
+pub use crate::breaker::ProxyBreaker;
+pub use crate::click::{Click, ClickPoint, SolveFailure, SolveProgress, SolveStep};
+pub use crate::client::{ClientManager, ClientOptions, ClientSpec, ConnStats, ProxyConfig};
+pub use crate::error::{Error, ErrorCode, Result};
+pub use crate::retry::RetryPolicy;
+pub use crate::slide::{GapDetection, Slide};
+pub use crate::timing::Timings;
+pub use crate::traffic::Traffic;
+pub use crate::w::{Easing, TrackOptions, WDebug, WOptions};
@@ -0,0 +1,128 @@
+// liveness.rs
+
+use crate::error::ErrorCode;
+use crate::sync::lock_or_recover;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// ### 存活状态
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub(crate) struct LivenessStatus {
+    pub(crate) healthy: bool,
+    /// 窗口内计入的请求数
+    pub(crate) samples: usize,
+    pub(crate) failures: usize,
+    pub(crate) failure_rate: f64,
+    pub(crate) window_secs: u64,
+}
+
+/// ### 按真实流量判断实例是否卡死
+/// - 记录窗口内求解请求的上游结果，失败率超过阈值时 /health 返回 503，让编排系统重启实例
+/// - 与深度健康检查不同，不主动探测极验，只反映生产流量
+/// - 一次成功会清空此前的失败，只有持续失败才会判为不健康
+pub(crate) struct Liveness {
+    window: Duration,
+    /// 失败率阈值，超过时判为不健康
+    max_failure_rate: f64,
+    /// 窗口内样本少于此数时不判定，避免刚启动或流量很小时误判
+    min_samples: usize,
+    /// (时间, 是否失败)，按时间先后排列
+    outcomes: Mutex<VecDeque<(Instant, bool)>>,
+}
+
+impl Liveness {
+    pub(crate) fn new(window: Duration, max_failure_rate: f64, min_samples: usize) -> Self {
+        Self {
+            window,
+            max_failure_rate,
+            min_samples: min_samples.max(1),
+            outcomes: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// ### 记录一次请求结果，`None` 表示成功
+    /// - 只有网络、上游超时、上游非 2xx 和内部错误计为失败
+    /// - 极验拒绝、参数错误、限流等与实例状态无关，不计入
+    pub(crate) fn record(&self, error: Option<ErrorCode>) {
+        let failed = match error {
+            None => false,
+            Some(
+                ErrorCode::UpstreamHttp
+                | ErrorCode::UpstreamTimeout
+                | ErrorCode::UpstreamStatus
+                | ErrorCode::Internal,
+            ) => true,
+            Some(_) => return,
+        };
+        let now = Instant::now();
+        let mut outcomes = lock_or_recover(&self.outcomes, "存活检查");
+        if !failed {
+            outcomes.clear();
+        }
+        outcomes.push_back((now, failed));
+        self.expire(&mut outcomes, now);
+    }
+
+    pub(crate) fn status(&self) -> LivenessStatus {
+        let mut outcomes = lock_or_recover(&self.outcomes, "存活检查");
+        self.expire(&mut outcomes, Instant::now());
+        let samples = outcomes.len();
+        let failures = outcomes.iter().filter(|(_, failed)| *failed).count();
+        let failure_rate = if samples == 0 { 0.0 } else { failures as f64 / samples as f64 };
+        LivenessStatus {
+            healthy: samples < self.min_samples || failure_rate <= self.max_failure_rate,
+            samples,
+            failures,
+            failure_rate,
+            window_secs: self.window.as_secs(),
+        }
+    }
+
+    /// 丢弃窗口之外的结果
+    fn expire(&self, outcomes: &mut VecDeque<(Instant, bool)>, now: Instant) {
+        while outcomes.front().is_some_and(|(at, _)| now.duration_since(*at) > self.window) {
+            outcomes.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_upstream_failures_turn_unhealthy_until_a_success() {
+        let liveness = Liveness::new(Duration::from_secs(60), 0.5, 3);
+        liveness.record(Some(ErrorCode::UpstreamTimeout));
+        liveness.record(Some(ErrorCode::UpstreamHttp));
+        // 样本不足时不判定
+        assert!(liveness.status().healthy);
+        // 与实例状态无关的错误不计入
+        liveness.record(Some(ErrorCode::Rejected));
+        assert_eq!(liveness.status().samples, 2);
+
+        liveness.record(Some(ErrorCode::UpstreamStatus));
+        let status = liveness.status();
+        assert!(!status.healthy);
+        assert_eq!(status.failures, 3);
+
+        liveness.record(None);
+        let status = liveness.status();
+        assert!(status.healthy);
+        assert_eq!((status.samples, status.failures), (1, 0));
+    }
+
+    #[test]
+    fn failures_outside_the_window_expire() {
+        let liveness = Liveness::new(Duration::from_millis(20), 0.5, 1);
+        liveness.record(Some(ErrorCode::UpstreamHttp));
+        assert!(!liveness.status().healthy);
+        std::thread::sleep(Duration::from_millis(40));
+        let status = liveness.status();
+        assert!(status.healthy);
+        assert_eq!(status.samples, 0);
+    }
+}
@@ -0,0 +1,141 @@
+// metrics.rs
+
+use crate::error::ErrorCode;
+use crate::liveness::Liveness;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// 耗时直方图的桶边界（秒）
+const LATENCY_BUCKETS: [f64; 10] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+#[derive(Default)]
+struct Histogram {
+    /// 与 LATENCY_BUCKETS 一一对应的累计计数
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, secs: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if secs <= bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += secs;
+        self.count += 1;
+    }
+}
+
+/// ### Prometheus 指标
+/// - 挂在 AppState 上，不使用全局静态变量
+/// - 只覆盖本服务关心的几项，手写文本格式输出，不引入额外依赖
+#[derive(Default)]
+pub(crate) struct Metrics {
+    /// 路由 -> 请求数
+    requests: Mutex<BTreeMap<&'static str, u64>>,
+    /// (路由, 结果) -> 次数，结果为 success、错误码或 cancelled（客户端断开）
+    outcomes: Mutex<BTreeMap<(&'static str, &'static str), u64>>,
+    /// 路由 -> 求解耗时
+    latency: Mutex<BTreeMap<&'static str, Histogram>>,
+    /// 设置 GT_LIVENESS_FAILURE_PERCENT 时同时把结果计入存活检查
+    liveness: Option<Arc<Liveness>>,
+}
+
+impl Metrics {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn with_liveness(liveness: Option<Arc<Liveness>>) -> Self {
+        Self { liveness, ..Self::default() }
+    }
+
+    pub(crate) fn record_request(&self, route: &'static str) {
+        if let Ok(mut requests) = self.requests.lock() {
+            *requests.entry(route).or_default() += 1;
+        }
+    }
+
+    /// 记录一次请求结果，`None` 表示成功
+    pub(crate) fn record_outcome(&self, route: &'static str, error: Option<ErrorCode>) {
+        let result = error.map_or("success", ErrorCode::as_str);
+        if let Ok(mut outcomes) = self.outcomes.lock() {
+            *outcomes.entry((route, result)).or_default() += 1;
+        }
+        if let Some(liveness) = &self.liveness {
+            liveness.record(error);
+        }
+    }
+
+    /// 记录一次因客户端断开而放弃的求解
+    pub(crate) fn record_cancelled(&self, route: &'static str) {
+        if let Ok(mut outcomes) = self.outcomes.lock() {
+            *outcomes.entry((route, "cancelled")).or_default() += 1;
+        }
+    }
+
+    pub(crate) fn observe_latency(&self, route: &'static str, elapsed: Duration) {
+        if let Ok(mut latency) = self.latency.lock() {
+            latency.entry(route).or_default().observe(elapsed.as_secs_f64());
+        }
+    }
+
+    /// ### 输出 Prometheus 文本格式
+    /// - sessions: (类型, 会话数)，在输出时实时统计
+    pub(crate) fn render(&self, sessions: &[(&str, usize)]) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP gt_requests_total 各路由请求总数\n");
+        out.push_str("# TYPE gt_requests_total counter\n");
+        if let Ok(requests) = self.requests.lock() {
+            for (route, count) in requests.iter() {
+                let _ = writeln!(out, "gt_requests_total{{route=\"{}\"}} {}", route, count);
+            }
+        }
+
+        out.push_str("# HELP gt_responses_total 各路由按结果（成功、错误码或 cancelled）统计的响应数\n");
+        out.push_str("# TYPE gt_responses_total counter\n");
+        if let Ok(outcomes) = self.outcomes.lock() {
+            for ((route, result), count) in outcomes.iter() {
+                let _ = writeln!(
+                    out,
+                    "gt_responses_total{{route=\"{}\",result=\"{}\"}} {}",
+                    route, result, count
+                );
+            }
+        }
+
+        out.push_str("# HELP gt_solve_duration_seconds 求解耗时\n");
+        out.push_str("# TYPE gt_solve_duration_seconds histogram\n");
+        if let Ok(latency) = self.latency.lock() {
+            for (route, histogram) in latency.iter() {
+                for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                    let _ = writeln!(
+                        out,
+                        "gt_solve_duration_seconds_bucket{{route=\"{}\",le=\"{}\"}} {}",
+                        route, bound, count
+                    );
+                }
+                let _ = writeln!(
+                    out,
+                    "gt_solve_duration_seconds_bucket{{route=\"{}\",le=\"+Inf\"}} {}",
+                    route, histogram.count
+                );
+                let _ = writeln!(out, "gt_solve_duration_seconds_sum{{route=\"{}\"}} {}", route, histogram.sum);
+                let _ = writeln!(out, "gt_solve_duration_seconds_count{{route=\"{}\"}} {}", route, histogram.count);
+            }
+        }
+
+        out.push_str("# HELP gt_active_sessions 当前缓存的会话数\n");
+        out.push_str("# TYPE gt_active_sessions gauge\n");
+        for (kind, count) in sessions {
+            let _ = writeln!(out, "gt_active_sessions{{kind=\"{}\"}} {}", kind, count);
+        }
+
+        out
+    }
+}
@@ -0,0 +1,247 @@
+// openapi.rs
+
+use crate::health::ProbeResult;
+use crate::session::SessionInfo;
+use crate::slide::GapDetection;
+use crate::w::WDecoded;
+use crate::{
+    ApiResponse, BatchStreamLine, BatchVerifyResult, CSResponse, DecodeWRequest, DetectGapRequest,
+    EgressRequest, EgressResponse, GenerateWRequest, GenerateWResponse, GetCSRequest,
+    GetTypeRequest, GetTypeResponse, KindVerifyRequest, OfflineGenerateWRequest, ProxyCheckRequest,
+    ProxyCheckResponse, ProxyStats, RefreshRequest, RefreshResponse, RegisterTestRequest,
+    RegisterTestResponse, ReloadResponse, SessionCountResponse, SessionCreateRequest,
+    SessionCreateResponse, SessionRemoveResponse, SimpleMatchRequest, SimpleMatchResponse,
+    SolveResponse, TestRequest, VerifyBatchRequest, VerifyRequest, VerifyResponse, VersionResponse,
+    WarmupRequest, WarmupResult,
+};
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+
+/// ### Swagger UI 页面
+/// - 从 CDN 加载 swagger-ui，读取同源的 /openapi.json
+pub(crate) const SWAGGER_UI: &str = r##"<!DOCTYPE html>
+<html lang="zh-CN">
+<head>
+  <meta charset="utf-8" />
+  <title>biliTicker_gt API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css" />
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>
+    window.ui = SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });
+  </script>
+</body>
+</html>
+"##;
+
+/// ### 文档构建器
+/// - 所有 schema 由同一个生成器生成，公共类型统一放入 components.schemas
+struct Builder {
+    gen: SchemaGenerator,
+    paths: Map<String, Value>,
+}
+
+impl Builder {
+    fn new() -> Self {
+        Self {
+            gen: SchemaSettings::openapi3().into_generator(),
+            paths: Map::new(),
+        }
+    }
+
+    fn schema<T: JsonSchema>(&mut self) -> Value {
+        serde_json::to_value(self.gen.subschema_for::<T>()).unwrap_or(Value::Null)
+    }
+
+    fn operation(&mut self, path: &str, method: &str, operation: Value) {
+        let item = self
+            .paths
+            .entry(path.to_string())
+            .or_insert_with(|| Value::Object(Map::new()));
+        item[method] = operation;
+    }
+
+    /// 统一的 ApiResponse 响应体，失败时 data 为空
+    fn responses<Res: JsonSchema>(&mut self) -> Value {
+        let ok = self.schema::<ApiResponse<Res>>();
+        let err = self.schema::<ApiResponse<()>>();
+        json!({
+            "200": { "description": "成功", "content": { "application/json": { "schema": ok } } },
+            "default": { "description": "失败，error_code 为错误码", "content": { "application/json": { "schema": err } } }
+        })
+    }
+
+    fn post<Req: JsonSchema, Res: JsonSchema>(&mut self, path: &str, summary: &str) {
+        let body = self.schema::<Req>();
+        let responses = self.responses::<Res>();
+        self.operation(
+            path,
+            "post",
+            json!({
+                "summary": summary,
+                "requestBody": { "required": true, "content": { "application/json": { "schema": body } } },
+                "responses": responses
+            }),
+        );
+    }
+
+    fn get<Res: JsonSchema>(&mut self, path: &str, summary: &str, parameters: Value) {
+        let responses = self.responses::<Res>();
+        self.operation(
+            path,
+            "get",
+            json!({ "summary": summary, "parameters": parameters, "responses": responses }),
+        );
+    }
+
+    fn delete<Res: JsonSchema>(&mut self, path: &str, summary: &str, parameters: Value) {
+        let responses = self.responses::<Res>();
+        self.operation(
+            path,
+            "delete",
+            json!({ "summary": summary, "parameters": parameters, "responses": responses }),
+        );
+    }
+
+    /// 非 ApiResponse 包装的纯文本接口
+    fn get_text(&mut self, path: &str, summary: &str) {
+        self.operation(
+            path,
+            "get",
+            json!({
+                "summary": summary,
+                "responses": { "200": { "description": "成功", "content": { "text/plain": { "schema": { "type": "string" } } } } }
+            }),
+        );
+    }
+
+    fn finish(self) -> Value {
+        let schemas = serde_json::to_value(self.gen.definitions()).unwrap_or(Value::Null);
+        json!({
+            "openapi": "3.0.3",
+            "info": {
+                "title": "biliTicker_gt",
+                "version": env!("CARGO_PKG_VERSION")
+            },
+            "paths": self.paths,
+            "components": { "schemas": schemas }
+        })
+    }
+}
+
+/// 会话接口共用的 kind 查询参数
+fn kind_param() -> Value {
+    json!({
+        "name": "kind",
+        "in": "query",
+        "required": false,
+        "description": "只操作指定类型的会话，省略时同时作用于点选和滑块",
+        "schema": { "type": "string", "enum": ["click", "slide"] }
+    })
+}
+
+/// GET 验证接口的查询参数，与 VerifyQuery 对应
+fn verify_query_params() -> Value {
+    let param = |name: &str, required: bool, ty: &str| {
+        json!({ "name": name, "in": "query", "required": required, "schema": { "type": ty } })
+    };
+    json!([
+        param("gt", true, "string"),
+        param("challenge", true, "string"),
+        param("w", false, "string"),
+        param("include_full", false, "boolean"),
+        param("include_timings", false, "boolean"),
+        param("session_id", false, "string"),
+    ])
+}
+
+/// ### 生成 OpenAPI 3 文档
+/// - 新增路由时需同步在这里登记
+pub(crate) fn document() -> Value {
+    let mut b = Builder::new();
+
+    b.get_text("/health", "存活检查，开启 GT_LIVENESS_FAILURE_PERCENT 且上游持续失败时返回 503");
+    b.get::<VersionResponse>("/version", "版本信息: crate 版本、git 提交、构建时间与 w 算法版本", json!([]));
+    b.get::<ProbeResult>("/health/deep", "深度健康检查，上游不可达时返回 503", json!([]));
+    b.get_text("/metrics", "Prometheus 指标");
+    b.get::<Vec<SessionInfo>>("/sessions", "列出缓存的会话", json!([kind_param()]));
+    b.delete::<SessionRemoveResponse>("/sessions", "清空缓存的会话", json!([kind_param()]));
+    b.get::<SessionCountResponse>("/sessions/count", "会话数量", json!([]));
+    b.post::<SessionCreateRequest, SessionCreateResponse>("/sessions/create", "批量预建会话并绑定代理");
+    b.delete::<SessionRemoveResponse>(
+        "/sessions/{id}",
+        "删除指定会话",
+        json!([
+            { "name": "id", "in": "path", "required": true, "schema": { "type": "string" } },
+            kind_param()
+        ]),
+    );
+    b.post::<WarmupRequest, Vec<WarmupResult>>("/warmup", "预热代理客户端，返回每个代理的结果");
+    b.post::<ProxyCheckRequest, ProxyCheckResponse>("/proxy/check", "检测代理是否可用，返回延迟与出口 IP");
+    b.get::<Vec<ProxyStats>>("/proxy/stats", "各代理的新建连接数与估算的连接复用次数", json!([]));
+    b.post::<EgressRequest, EgressResponse>("/debug/egress", "按求解时的客户端选择规则检查会话的出口 IP");
+    let responses = b.responses::<ReloadResponse>();
+    b.operation(
+        "/admin/reload",
+        "post",
+        json!({
+            "summary": "重载客户端配置并清空客户端缓存，需设置 GT_ADMIN_SECRET",
+            "parameters": [
+                { "name": "X-Admin-Secret", "in": "header", "required": true, "schema": { "type": "string" } }
+            ],
+            "responses": responses
+        }),
+    );
+    b.post::<KindVerifyRequest, VerifyResponse>("/verify", "按 kind 分发的统一验证接口");
+    b.post::<OfflineGenerateWRequest, GenerateWResponse>("/generate_w", "离线生成 w 参数，不访问网络");
+    b.operation(
+        "/ws/solve",
+        "get",
+        json!({
+            "summary": "WebSocket 求解，逐步推送 registered / type_detected / w_generated / verified 事件",
+            "responses": { "101": { "description": "切换到 WebSocket 协议" } }
+        }),
+    );
+
+    b.post::<SimpleMatchRequest, SimpleMatchResponse>("/click/simple_match", "点选一键求解");
+    b.post::<SimpleMatchRequest, SimpleMatchResponse>("/click/simple_match_retry", "点选一键求解（失败自动刷新重试）");
+    b.post::<TestRequest, SolveResponse>("/click/solve", "点选分步求解，失败时返回失败的步骤与已得到的中间值");
+    b.post::<TestRequest, SolveResponse>("/click/prepare", "点选只生成 w 不调用 verify，返回 gt、challenge、c、s、w");
+    b.post::<SimpleMatchRequest, SimpleMatchResponse>("/slide/simple_match", "滑块一键求解");
+    b.post::<SimpleMatchRequest, SimpleMatchResponse>("/slide/simple_match_retry", "滑块一键求解（失败自动刷新重试）");
+
+    for kind in ["click", "slide"] {
+        b.post::<RegisterTestRequest, RegisterTestResponse>(&format!("/{}/register_test", kind), "获取测试用 gt 与 challenge");
+        b.post::<GetCSRequest, CSResponse>(&format!("/{}/get_c_s", kind), "获取 c 与 s");
+        b.post::<GetTypeRequest, GetTypeResponse>(&format!("/{}/get_type", kind), "获取验证类型");
+        b.post::<VerifyRequest, VerifyResponse>(&format!("/{}/verify", kind), "提交验证");
+        b.get::<VerifyResponse>(&format!("/{}/verify", kind), "以查询参数提交验证，不支持代理与 Cookie", verify_query_params());
+        b.post::<VerifyBatchRequest, Vec<ApiResponse<BatchVerifyResult>>>(
+            &format!("/{}/verify_batch", kind),
+            "批量提交验证，结果按请求顺序返回",
+        );
+        let body = b.schema::<VerifyBatchRequest>();
+        let line = b.schema::<BatchStreamLine>();
+        b.operation(
+            &format!("/{}/verify_stream", kind),
+            "post",
+            json!({
+                "summary": "流式批量提交验证，每项完成后输出一行 NDJSON，带上该项的序号",
+                "requestBody": { "required": true, "content": { "application/json": { "schema": body } } },
+                "responses": {
+                    "200": { "description": "每行一个结果，顺序与请求不一定相同", "content": { "application/x-ndjson": { "schema": line } } }
+                }
+            }),
+        );
+        b.post::<GenerateWRequest, GenerateWResponse>(&format!("/{}/generate_w", kind), "生成 w 参数");
+        b.post::<DecodeWRequest, WDecoded>(&format!("/{}/decode_w", kind), "解析 w，检查格式并核对其中的参数");
+        b.post::<RefreshRequest, RefreshResponse>(&format!("/{}/refresh", kind), "刷新验证码，取得新的 challenge");
+        b.post::<TestRequest, String>(&format!("/{}/test", kind), "使用测试地址完整跑一遍");
+    }
+    b.post::<DetectGapRequest, GapDetection>("/slide/detect_gap", "只识别滑块缺口位置，不生成 w 也不提交验证");
+
+    b.finish()
+}
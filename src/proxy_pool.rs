@@ -0,0 +1,65 @@
+// proxy_pool.rs
+
+use crate::client::ProxyConfig;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// ### 服务端代理池
+/// - 由 GT_PROXY_POOL 配置，请求设置 use_pool 时从这里取代理
+/// - 每次取用从下一个代理开始轮转，分摊各代理的流量
+/// - 熔断中的代理排到最后，其余代理都不可用时才会尝试
+pub(crate) struct ProxyPool {
+    proxies: Vec<ProxyConfig>,
+    next: AtomicUsize,
+}
+
+impl ProxyPool {
+    pub(crate) fn new(proxies: Vec<ProxyConfig>) -> Self {
+        Self { proxies, next: AtomicUsize::new(0) }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.proxies.is_empty()
+    }
+
+    /// ### 本次请求依次尝试的代理
+    /// #### 参数
+    /// - `is_open`: 代理是否处于熔断中
+    pub(crate) fn candidates(&self, is_open: impl Fn(&ProxyConfig) -> bool) -> Vec<ProxyConfig> {
+        if self.proxies.is_empty() {
+            return Vec::new();
+        }
+        let start = self.next.fetch_add(1, Ordering::Relaxed) % self.proxies.len();
+        let (mut healthy, open): (Vec<_>, Vec<_>) = self.proxies[start..]
+            .iter()
+            .chain(&self.proxies[..start])
+            .cloned()
+            .partition(|proxy| !is_open(proxy));
+        healthy.extend(open);
+        healthy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn urls(proxies: Vec<ProxyConfig>) -> Vec<String> {
+        proxies.into_iter().map(|proxy| proxy.url).collect()
+    }
+
+    #[test]
+    fn candidates_rotate_and_put_open_proxies_last() {
+        let pool = ProxyPool::new(
+            ["http://127.0.0.1:1", "http://127.0.0.1:2", "http://127.0.0.1:3"]
+                .map(|url| ProxyConfig::from(url.to_string()))
+                .to_vec(),
+        );
+        let healthy = |_: &ProxyConfig| false;
+        assert_eq!(urls(pool.candidates(healthy)), ["http://127.0.0.1:1", "http://127.0.0.1:2", "http://127.0.0.1:3"]);
+        assert_eq!(urls(pool.candidates(healthy)), ["http://127.0.0.1:2", "http://127.0.0.1:3", "http://127.0.0.1:1"]);
+
+        let open = |proxy: &ProxyConfig| proxy.url.ends_with(":1");
+        assert_eq!(urls(pool.candidates(open)), ["http://127.0.0.1:3", "http://127.0.0.1:2", "http://127.0.0.1:1"]);
+        assert!(ProxyPool::new(Vec::new()).candidates(healthy).is_empty());
+    }
+}
@@ -0,0 +1,58 @@
+// rate_limit.rs
+
+use crate::sync::lock_or_recover;
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 最多跟踪的 session_id 数量，超出后淘汰最久未访问的令牌桶
+const MAX_BUCKETS: usize = 4096;
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// ### 按 session_id 的令牌桶限流器
+/// - rate: 每秒补充的令牌数，为 0 时不限流
+/// - burst: 桶容量，即允许的突发请求数
+pub(crate) struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    buckets: Mutex<LruCache<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(rate: u64, burst: u64) -> Self {
+        Self {
+            rate: rate as f64,
+            burst: burst.max(1) as f64,
+            buckets: Mutex::new(LruCache::new(NonZeroUsize::new(MAX_BUCKETS).unwrap())),
+        }
+    }
+
+    /// ### 消耗一个令牌
+    /// #### 返回值
+    /// - 令牌不足时返回需要等待的时间
+    pub(crate) fn check(&self, key: &str) -> Result<(), Duration> {
+        if self.rate <= 0.0 {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let mut buckets = lock_or_recover(&self.buckets, "限流器");
+        let bucket = buckets.get_or_insert_mut(key.to_string(), || Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate))
+        }
+    }
+}
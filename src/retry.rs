@@ -0,0 +1,50 @@
+// retry.rs
+
+use crate::error::Error;
+use rand::{thread_rng, Rng};
+use std::time::Duration;
+
+/// 极验未给出 Retry-After 时的限流等待时间
+pub const DEFAULT_RATE_LIMIT_WAIT: Duration = Duration::from_secs(1);
+/// 限流等待时间上限，Retry-After 过大时截断，避免长时间占用阻塞线程
+pub const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(30);
+
+/// ### 极验限流后重试前应等待的时间
+/// - 不是限流错误时返回 None，调用方按原逻辑立即重试
+/// - 按 Retry-After 等待，缺失时为 DEFAULT_RATE_LIMIT_WAIT，最多 MAX_RATE_LIMIT_WAIT
+pub fn rate_limit_wait(e: &Error) -> Option<Duration> {
+    e.is_rate_limited()
+        .then(|| e.retry_after().unwrap_or(DEFAULT_RATE_LIMIT_WAIT).min(MAX_RATE_LIMIT_WAIT))
+}
+
+/// ### 重试策略
+/// - max_retries: 首次尝试之外最多重试的次数
+/// - base_delay: 第一次重试前的基础等待时间，之后按指数增长
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// 默认基础等待时间
+    pub const DEFAULT_BASE_DELAY: Duration = Duration::from_millis(500);
+
+    pub fn new(max_retries: u32, base_delay: Duration) -> Self {
+        Self {
+            max_retries,
+            base_delay,
+        }
+    }
+
+    /// ### 第 attempt 次尝试失败后的等待时间
+    /// - 指数退避: base * 2^(attempt-1)
+    /// - 抖动: 取退避时间的一半加上 [0, 一半] 内的随机值，避免多个请求同时重试
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exp = attempt.saturating_sub(1).min(16);
+        let backoff = self.base_delay.saturating_mul(1u32 << exp);
+        let half = backoff / 2;
+        let jitter_ms = thread_rng().gen_range(0..=half.as_millis() as u64);
+        half + Duration::from_millis(jitter_ms)
+    }
+}
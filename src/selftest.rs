@@ -0,0 +1,55 @@
+// selftest.rs
+
+use crate::abstraction::Test;
+use crate::click::Click;
+use crate::client::{ClientManager, ClientSpec};
+use crate::config;
+use crate::slide::Slide;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// ### 自检
+/// - `--selftest` 启动时不监听端口，依次对点选和滑块跑一遍完整求解（Test::test）
+/// - 每项打印通过/失败与耗时，任一项失败时返回 false，由调用方以非零状态码退出
+/// - 验证码申请地址见 GT_SELFTEST_CLICK_URL / GT_SELFTEST_SLIDE_URL
+pub(crate) fn run() -> bool {
+    let manager = ClientManager::new(config::client_options());
+    let client = match manager.get(&ClientSpec::default()) {
+        Ok(client) => client,
+        Err(e) => {
+            println!("自检失败: 创建客户端失败: {}", e);
+            return false;
+        }
+    };
+    let base_url = config::geetest_base_url();
+    let (click_url, slide_url) = config::selftest_urls();
+
+    let mut passed = true;
+    passed &= check("click", || {
+        let mut click = Click::new(Arc::clone(&client), Arc::clone(&client));
+        click.set_base_url(base_url.as_str());
+        click.test(&click_url)
+    });
+    passed &= check("slide", || {
+        let mut slide = Slide::new(Arc::clone(&client), Arc::clone(&client));
+        slide.set_base_url(base_url.as_str());
+        slide.test(&slide_url)
+    });
+    passed
+}
+
+fn check(name: &str, solve: impl FnOnce() -> crate::error::Result<String>) -> bool {
+    let started = Instant::now();
+    let res = solve();
+    let elapsed = started.elapsed().as_secs_f64();
+    match res {
+        Ok(validate) => {
+            println!("{}: 通过 ({:.2}s) validate={}", name, elapsed, validate);
+            true
+        }
+        Err(e) => {
+            println!("{}: 失败 ({:.2}s) [{}] {}", name, elapsed, e.code().as_str(), e);
+            false
+        }
+    }
+}
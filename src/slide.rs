@@ -1,24 +1,92 @@
 // slide.rs
 
-use crate::abstraction::{Api, GenerateW, Test, VerifyType};
-use crate::error::{
-    missing_param, net_work_error, other, other_without_source, parse_error, Result,
+use crate::abstraction::{
+    jsonp_callback, parse_c, parse_jsonp, parse_s, send_text, verify_payload, Api,
+    CapturedImage, GenerateW, Test, VerifyPayload, VerifyType, DEFAULT_BASE_URL,
+    DEFAULT_MAX_IMAGE_BYTES,
 };
-use crate::w::slide_calculate;
+use crate::breaker::ProxyBreaker;
+use crate::client::ConnStats;
+use crate::error::{exhausted, missing_param, other, other_without_source, rejected, Result};
+use crate::retry::{rate_limit_wait, RetryPolicy};
+use crate::timing::{Stopwatch, Timings};
+use crate::traffic::Traffic;
+use crate::w::{self, WDebug, WOptions};
 use captcha_breaker::captcha::Slide0;
 use image::{DynamicImage, GenericImage};
 use reqwest::blocking::Client;
+use schemars::JsonSchema;
+use serde::Serialize;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::sync::Arc;
-// 修改：引入 SystemTime 和 UNIX_EPOCH 用于生成时间戳
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::thread::sleep;
+
+/// ### 滑块缺口识别结果
+/// - gap: 缺口的横向像素偏移，即 calculate_key 得到的 key
+/// - image_width / image_height: 识别所用背景图（还原乱序后）的尺寸
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct GapDetection {
+    pub gap: u32,
+    pub image_width: u32,
+    pub image_height: u32,
+}
+
+/// ### 识别滑块缺口位置
+/// - bg 为极验下发的乱序背景图，先按固定顺序还原为 260x160 再识别
+/// - 只做视觉识别，不访问网络，也不生成 w
+pub fn detect_gap(bg: &[u8], slice: &[u8]) -> Result<GapDetection> {
+    let slice_img = image::load_from_memory(slice).map_err(|e| other("内部错误", e))?;
+    let bg_img = image::load_from_memory(bg).map_err(|e| other("图片解析错误", e))?;
+    let mut new_bg_img = image::ImageBuffer::new(260, 160);
+    let offset = [
+        39, 38, 48, 49, 41, 40, 46, 47, 35, 34, 50, 51, 33, 32, 28, 29, 27, 26, 36, 37, 31, 30,
+        44, 45, 43, 42, 12, 13, 23, 22, 14, 15, 21, 20, 8, 9, 25, 24, 6, 7, 3, 2, 0, 1, 11, 10,
+        4, 5, 19, 18, 16, 17,
+    ];
+    let (w_sep, h_sep) = (10u32, 80u32);
+    for idx in 0..52 {
+        let x = (offset[idx] % 26 * 12) as u32;
+        let y = if offset[idx] > 25 { h_sep } else { 0 };
+        let new_x = (idx % 26 * 10) as u32;
+        let new_y = if idx > 25 { h_sep } else { 0 };
+
+        let pi = bg_img.crop_imm(x, y, w_sep, h_sep);
+        new_bg_img.copy_from(&pi, new_x, new_y).unwrap();
+    }
+    let (image_width, image_height) = new_bg_img.dimensions();
+    let new_bg_img = DynamicImage::ImageRgba8(new_bg_img);
+    let gap = Slide0::run(&slice_img, &new_bg_img)
+        .map_err(|_| other_without_source("滑块识别内部错误"))?
+        .x1;
+    Ok(GapDetection { gap, image_width, image_height })
+}
 
 #[derive(Clone)]
 pub struct Slide {
     client: Arc<Client>,
     noproxy_client: Arc<Client>,
+    /// 当前代理的连接统计，由服务端按代理设置
+    conn_stats: Option<Arc<ConnStats>>,
+    /// 当前代理的熔断句柄，由服务端按实际使用的代理设置
+    breaker: Option<ProxyBreaker>,
+    /// 当前客户端使用的代理，已去掉认证信息，由服务端设置
+    proxy_label: Option<String>,
+    /// 图片下载大小上限（字节）
+    max_image_bytes: u64,
     verify_type: VerifyType,
+    cookies: Option<String>,
+    /// 目标站点的 Cookie 请求头，只附加到 register_test 请求上
+    target_cookies: Option<String>,
+    referer: Option<String>,
+    base_url: String,
+    traffic: Option<Arc<Traffic>>,
+    w_options: WOptions,
+    /// 为 true 时保留下载的图片
+    capture_images: bool,
+    captured_images: Vec<CapturedImage>,
+    #[cfg(feature = "async-client")]
+    async_client: Option<reqwest::Client>,
 }
 
 impl Slide {
@@ -26,13 +94,314 @@ impl Slide {
         Slide {
             client,
             noproxy_client,
+            conn_stats: None,
+            breaker: None,
+            proxy_label: None,
+            max_image_bytes: DEFAULT_MAX_IMAGE_BYTES,
             verify_type: VerifyType::Slide,
+            cookies: None,
+            target_cookies: None,
+            referer: None,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            traffic: None,
+            w_options: WOptions::default(),
+            capture_images: false,
+            captured_images: Vec::new(),
+            #[cfg(feature = "async-client")]
+            async_client: None,
         }
     }
 
     pub fn update_client(&mut self, new_client: Arc<Client>) {
         self.client = new_client;
     }
+
+    /// 替换不走代理的客户端（用于下载图片等请求）
+    pub fn update_noproxy_client(&mut self, new_client: Arc<Client>) {
+        self.noproxy_client = new_client;
+    }
+
+    /// 设置图片下载大小上限（字节），默认 `DEFAULT_MAX_IMAGE_BYTES`
+    pub fn set_max_image_bytes(&mut self, limit: u64) {
+        self.max_image_bytes = limit;
+    }
+
+    /// 设置当前客户端使用的代理，应去掉认证信息，不走代理时为 None；只用于展示，不影响请求
+    pub fn set_proxy_label(&mut self, label: Option<String>) {
+        self.proxy_label = label;
+    }
+
+    /// 当前客户端使用的代理，不走代理时为 None
+    pub fn proxy_label(&self) -> Option<&str> {
+        self.proxy_label.as_deref()
+    }
+
+    /// 设置当前代理的连接统计，每次经 client() 发出同步请求时计数
+    pub fn set_conn_stats(&mut self, stats: Option<Arc<ConnStats>>) {
+        self.conn_stats = stats;
+    }
+
+    /// 设置当前代理的熔断句柄，服务端据此回报求解结果
+    pub fn set_breaker(&mut self, breaker: Option<ProxyBreaker>) {
+        self.breaker = breaker;
+    }
+
+    /// 设置极验接口地址，替换默认的 `DEFAULT_BASE_URL`
+    pub fn set_base_url(&mut self, base_url: impl Into<String>) {
+        self.base_url = base_url.into();
+    }
+
+    /// 设置极验请求的录制或回放，None 时直接访问网络
+    pub fn set_traffic(&mut self, traffic: Option<Arc<Traffic>>) {
+        self.traffic = traffic;
+    }
+
+    /// 设置附加到极验请求上的 Cookie 请求头，空字符串视为未设置
+    pub fn set_cookies(&mut self, cookies: Option<String>) {
+        self.cookies = cookies.filter(|c| !c.trim().is_empty());
+    }
+
+    /// 设置目标站点的 Cookie 请求头，用于需要登录才能申请验证码的站点；只发往 register_test 的地址，不发给极验
+    pub fn set_target_cookies(&mut self, cookies: Option<String>) {
+        self.target_cookies = cookies.filter(|c| !c.trim().is_empty());
+    }
+
+    /// 设置 Referer 请求头，附加到 register_test 以及之后发往极验的每个请求上，空字符串视为未设置
+    pub fn set_referer(&mut self, referer: Option<String>) {
+        self.referer = referer.filter(|r| !r.trim().is_empty());
+    }
+
+    /// 设置生成 w 时使用的轨迹参数与随机数种子
+    pub fn set_w_options(&mut self, options: WOptions) {
+        self.w_options = options;
+    }
+
+    /// 设置生成 w 时使用的算法版本，None 为最新版本；不改动 w_options 中的其他参数
+    pub fn set_algo_version(&mut self, version: Option<u32>) {
+        self.w_options.algo_version = version;
+    }
+
+    /// 设置为 true 时保留求解过程中下载的图片，之后用 `take_images` 取出，同时丢弃之前保留的图片
+    pub fn set_capture_images(&mut self, capture: bool) {
+        self.capture_images = capture;
+        self.captured_images.clear();
+    }
+
+    /// 取出已保留的图片，按下载顺序排列
+    pub fn take_images(&mut self) -> Vec<CapturedImage> {
+        std::mem::take(&mut self.captured_images)
+    }
+
+    /// 下载图片，开启 capture_images 时同时保留一份
+    fn fetch_img(&mut self, url: &str) -> Result<Vec<u8>> {
+        let bytes = self.download_img(url)?;
+        if self.capture_images {
+            self.captured_images.push(CapturedImage { url: url.to_string(), bytes: bytes.clone() });
+        }
+        Ok(bytes)
+    }
+
+    /// 设置异步路径使用的客户端
+    #[cfg(feature = "async-client")]
+    pub fn set_async_client(&mut self, client: reqwest::Client) {
+        self.async_client = Some(client);
+    }
+
+    #[cfg(feature = "async-client")]
+    fn async_client(&self) -> Result<&reqwest::Client> {
+        self.async_client
+            .as_ref()
+            .ok_or_else(|| other_without_source("异步客户端未初始化"))
+    }
+
+    /// ### 异步获取c和s参数
+    #[cfg(feature = "async-client")]
+    pub async fn get_c_s_async(
+        &self,
+        gt: &str,
+        challenge: &str,
+        w: Option<&str>,
+    ) -> Result<(Vec<u8>, String)> {
+        self.get_c_s_timed_async(gt, challenge, w).await.map(|(c, s, _)| (c, s))
+    }
+
+    /// ### 异步获取c和s参数并统计耗时
+    #[cfg(feature = "async-client")]
+    pub async fn get_c_s_timed_async(
+        &self,
+        gt: &str,
+        challenge: &str,
+        w: Option<&str>,
+    ) -> Result<(Vec<u8>, String, Timings)> {
+        self.record_request();
+        crate::abstraction::get_c_s_async(self.async_client()?, self.traffic(), &self.base_url, gt, challenge, w, self.cookies(), self.referer()).await
+    }
+
+    /// ### 异步验证
+    /// #### 返回值
+    /// - message
+    /// - validate
+    #[cfg(feature = "async-client")]
+    pub async fn verify_async(
+        &self,
+        gt: &str,
+        challenge: &str,
+        w: Option<&str>,
+    ) -> Result<(String, String)> {
+        self.verify_full_async(gt, challenge, w)
+            .await
+            .map(|(message, validate, _)| (message, validate))
+    }
+
+    /// ### 异步验证并保留极验的完整响应
+    /// #### 返回值
+    /// - message
+    /// - validate
+    /// - 完整验证结果
+    #[cfg(feature = "async-client")]
+    pub async fn verify_full_async(
+        &self,
+        gt: &str,
+        challenge: &str,
+        w: Option<&str>,
+    ) -> Result<(String, String, VerifyPayload)> {
+        self.verify_full_timed_async(gt, challenge, w)
+            .await
+            .map(|(message, validate, payload, _)| (message, validate, payload))
+    }
+
+    /// ### 异步验证并统计耗时
+    #[cfg(feature = "async-client")]
+    pub async fn verify_full_timed_async(
+        &self,
+        gt: &str,
+        challenge: &str,
+        w: Option<&str>,
+    ) -> Result<(String, String, VerifyPayload, Timings)> {
+        let mut watch = Stopwatch::start();
+        let callback = jsonp_callback();
+
+        let url = self.endpoint("ajax.php");
+        let params = verify_params(gt, challenge, callback.as_str(), w);
+        let mut builder = self.async_client()?.get(url);
+        if let Some(cookies) = self.cookies() {
+            builder = builder.header(reqwest::header::COOKIE, cookies);
+        }
+        if let Some(referer) = self.referer() {
+            builder = builder.header(reqwest::header::REFERER, referer);
+        }
+        self.record_request();
+        let res = watch
+            .network_async(crate::abstraction::send_text_async(builder.query(&params), self.traffic()))
+            .await?;
+
+        let (message, validate, payload) = parse_verify(&parse_jsonp(&res, &callback)?, challenge)?;
+        Ok((message, validate, payload, watch.finish()))
+    }
+
+    /// ### 异步生成w
+    /// - 滑块的w只依赖本地计算，不涉及网络
+    #[cfg(feature = "async-client")]
+    pub async fn generate_w_async(
+        &self,
+        key: &str,
+        gt: &str,
+        challenge: &str,
+        c: &[u8],
+        s: &str,
+    ) -> Result<String> {
+        self.generate_w(key, gt, challenge, c, s)
+    }
+}
+
+impl Slide {
+    /// ### 一键求解
+    /// - 与 `Click::simple_match` 相同的流程；滑块获取图片时极验会下发新的 challenge，
+    ///   之后的 w 与验证都使用新 challenge
+    /// #### 返回值
+    /// - validate
+    pub fn simple_match(&mut self, gt: &str, challenge: &str) -> Result<String> {
+        let (c, s) = self.get_c_s(gt, challenge, None)?;
+        self.get_type(gt, challenge, None)?;
+        let (_, _, args) = self.get_new_c_s_args(gt, challenge)?;
+        self.vvv(gt, &c, &s, args)
+    }
+
+    /// ### 只识别缺口，不生成 w 也不提交验证
+    /// - 与 simple_match 相同地申请图片，用于单独评估识别准确率
+    pub fn detect_gap(&mut self, gt: &str, challenge: &str) -> Result<GapDetection> {
+        self.get_c_s(gt, challenge, None)?;
+        self.get_type(gt, challenge, None)?;
+        let (_, _, (_, _, bg, slice)) = self.get_new_c_s_args(gt, challenge)?;
+        let bg_img = self.fetch_img(bg.as_str())?;
+        let slice_img = self.fetch_img(slice.as_str())?;
+        detect_gap(&bg_img, &slice_img)
+    }
+
+    /// ### 一键求解，验证失败时刷新图片重试
+    /// - 与 `Click::simple_match_retry` 相同，最多尝试 max_attempts 次
+    /// #### 返回值
+    /// - validate
+    /// - 成功的是第几次尝试
+    pub fn simple_match_retry(&mut self, gt: &str, challenge: &str, max_attempts: u32) -> Result<(String, u32)> {
+        let (c, s) = self.get_c_s(gt, challenge, None)?;
+        self.get_type(gt, challenge, None)?;
+        let (_, _, mut args) = self.get_new_c_s_args(gt, challenge)?;
+        let max_attempts = max_attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            let challenge = args.0.clone();
+            match self.vvv(gt, &c, &s, args) {
+                Ok(validate) => return Ok((validate, attempt)),
+                Err(e) if attempt >= max_attempts => return Err(exhausted(attempt, e)),
+                Err(e) => match rate_limit_wait(&e) {
+                    // 立即重试只会加重限流
+                    Some(wait) => {
+                        tracing::warn!("第 {}/{} 次尝试被极验限流，{:?} 后重试: {}", attempt, max_attempts, wait, e);
+                        sleep(wait);
+                    }
+                    None => tracing::warn!("第 {}/{} 次尝试失败，刷新图片后重试: {}", attempt, max_attempts, e),
+                },
+            }
+            attempt += 1;
+            args = self.refresh(gt, &challenge)?;
+        }
+    }
+
+    /// ### 带退避重试的 simple_match
+    /// - 与 `Click::simple_match_backoff` 相同，只有临时错误才会重试
+    /// #### 返回值
+    /// - validate
+    /// - 实际尝试次数
+    pub fn simple_match_backoff(
+        &mut self,
+        gt: &str,
+        challenge: &str,
+        policy: &RetryPolicy,
+    ) -> Result<(String, u32)> {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            match self.simple_match(gt, challenge) {
+                Ok(validate) => return Ok((validate, attempt)),
+                Err(e) if e.is_transient() && attempt <= policy.max_retries => {
+                    let delay = policy.delay(attempt);
+                    tracing::warn!("第 {} 次尝试失败，{:?} 后重试: {}", attempt, delay, e);
+                    sleep(delay);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// 计算缺口位置、生成 w 并验证，challenge 取 args 中极验下发的新值
+    fn vvv(&mut self, gt: &str, c: &[u8], s: &str, args: <Self as Api>::ArgsType) -> Result<String> {
+        let challenge = args.0.clone();
+        let key = self.calculate_key(args)?;
+        let w = self.generate_w(key.as_str(), gt, &challenge, c, s)?;
+        let (_, validate) = self.verify(gt, &challenge, Some(w.as_str()))?;
+        Ok(validate)
+    }
 }
 
 impl Api for Slide {
@@ -45,20 +414,45 @@ impl Api for Slide {
         &self.noproxy_client
     }
 
+    fn max_image_bytes(&self) -> u64 {
+        self.max_image_bytes
+    }
+
+    fn conn_stats(&self) -> Option<&ConnStats> {
+        self.conn_stats.as_deref()
+    }
+
+    fn breaker(&self) -> Option<&ProxyBreaker> {
+        self.breaker.as_ref()
+    }
+
+    fn cookies(&self) -> Option<&str> {
+        self.cookies.as_deref()
+    }
+
+    fn target_cookies(&self) -> Option<&str> {
+        self.target_cookies.as_deref()
+    }
+
+    fn referer(&self) -> Option<&str> {
+        self.referer.as_deref()
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn traffic(&self) -> Option<&Traffic> {
+        self.traffic.as_deref()
+    }
+
     fn get_new_c_s_args(
         &self,
         gt: &str,
         challenge: &str,
     ) -> Result<(Vec<u8>, String, Self::ArgsType)> {
-        // 修改：生成动态回调
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("Time went backwards")
-            .as_millis()
-            .to_string();
-        let callback = format!("geetest_{}", timestamp);
-
-        let url = "http://api.geetest.com/get.php";
+        let callback = jsonp_callback();
+
         let mut params = HashMap::from([
             ("gt", gt),
             ("challenge", challenge),
@@ -67,180 +461,137 @@ impl Api for Slide {
             ("isPC", "true"),
             ("callback", callback.as_str()), // 使用动态回调
         ]);
-        params.insert(
-            "type",
-            match self.verify_type {
-                VerifyType::Click => "click",
-                VerifyType::Slide => "slide",
-            },
-        );
-        let res = self
-            .client
-            .get(url)
-            .query(&params)
-            .send()
-            .map_err(net_work_error)?;
-        let res = res.text().map_err(|e| other("什么b玩意错误", e))?;
-
-        // 修改：使用动态回调作为前缀
-        let prefix = format!("{}(", callback);
-        let res = res
-            .strip_prefix(&prefix)
-            .ok_or_else(|| other_without_source("前缀错误"))?
-            .strip_suffix(")")
-            .ok_or_else(|| other_without_source("后缀错误"))?;
-
-        let res: Value = serde_json::from_str(res).map_err(parse_error)?;
-        let c: Vec<u8> =
-            serde_json::from_value(res.get("c").ok_or_else(|| missing_param("c"))?.clone())
-                .map_err(parse_error)?;
-        let static_server = res
-            .get("static_servers")
-            .ok_or_else(|| missing_param("static_servers"))?
-            .as_array()
-            .ok_or_else(|| missing_param("static_servers"))?
-            .get(0)
-            .ok_or_else(|| other_without_source("static_servers里面咋没东西啊"))?
-            .as_str()
-            .ok_or_else(|| other_without_source("static_servers里面咋没东西啊"))?;
+        params.insert("type", self.verify_type.as_str());
+        self.record_request();
+        let res = send_text(self.geetest_get("get.php").query(&params), self.traffic())?;
+        let res = parse_jsonp(&res, &callback)?;
+        let c = parse_c(&res)?;
 
-        Ok((
-            c,
-            // 改进：使用安全的错误处理替换 expect 和 unwrap
-            res.get("s")
-                .ok_or_else(|| missing_param("s"))?
-                .as_str()
-                .ok_or_else(|| missing_param("s"))?
-                .to_string(),
-            (
-                res.get("challenge")
-                    .ok_or_else(|| missing_param("challenge"))?
-                    .as_str()
-                    .ok_or_else(|| missing_param("challenge"))?
-                    .to_string(),
-                format!(
-                    "https://{}{}",
-                    static_server,
-                    res.get("fullbg")
-                        .ok_or_else(|| missing_param("fullbg"))?
-                        .as_str()
-                        .ok_or_else(|| missing_param("fullbg"))?
-                ),
-                format!(
-                    "https://{}{}",
-                    static_server,
-                    res.get("bg")
-                        .ok_or_else(|| missing_param("bg"))?
-                        .as_str()
-                        .ok_or_else(|| missing_param("bg"))?
-                ),
-                format!(
-                    "https://{}{}",
-                    static_server,
-                    res.get("slice")
-                        .ok_or_else(|| missing_param("slice"))?
-                        .as_str()
-                        .ok_or_else(|| missing_param("slice"))?
-                ),
-            ),
-        ))
+        Ok((c, parse_s(&res)?, parse_args(&res)?))
     }
 
-    fn verify(&self, gt: &str, challenge: &str, w: Option<&str>) -> Result<(String, String)> {
+    fn verify_full_timed(
+        &self,
+        gt: &str,
+        challenge: &str,
+        w: Option<&str>,
+    ) -> Result<(String, String, VerifyPayload, Timings)> {
+        let mut watch = Stopwatch::start();
         // 修改：生成动态回调
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("Time went backwards")
-            .as_millis()
-            .to_string();
-        let callback = format!("geetest_{}", timestamp);
-
-        let url = "http://api.geetest.com/ajax.php";
-        let mut params = HashMap::from([
+        let callback = jsonp_callback();
+
+        let params = verify_params(gt, challenge, callback.as_str(), w);
+        let builder = self.geetest_get("ajax.php").query(&params);
+        self.record_request();
+        let res = watch.network(|| send_text(builder, self.traffic()))?;
+
+        let (message, validate, payload) = parse_verify(&parse_jsonp(&res, &callback)?, challenge)?;
+        Ok((message, validate, payload, watch.finish()))
+    }
+
+    fn refresh(&self, gt: &str, challenge: &str) -> Result<Self::ArgsType> {
+        let callback = jsonp_callback();
+
+        let params = HashMap::from([
             ("gt", gt),
             ("challenge", challenge),
-            ("callback", callback.as_str()), // 使用动态回调
+            ("callback", callback.as_str()),
         ]);
-        if let Some(w) = w {
-            params.insert("w", w);
-        }
-        let res = self
-            .client()
-            .get(url)
-            .query(&params)
-            .send()
-            .map_err(net_work_error)?;
-        // 改进：使用安全的错误处理替换 unwrap
-        let res = res.text().map_err(|e| other("响应转文本失败", e))?;
-
-        // 修改：使用动态回调作为前缀
-        let prefix = format!("{}(", callback);
-        let res = res
-            .strip_prefix(&prefix)
-            .ok_or_else(|| other_without_source("前缀错误"))?
-            .strip_suffix(")")
-            .ok_or_else(|| other_without_source("后缀错误"))?;
-
-        // 改进：使用安全的错误处理替换 unwrap
-        let res: Value = serde_json::from_str(res).map_err(parse_error)?;
-        Ok((
-            res.get("message")
-                .ok_or_else(|| missing_param("message"))?
-                .as_str()
-                .ok_or_else(|| missing_param("message"))?
-                .to_string(),
-            res.get("validate")
-                .ok_or_else(|| missing_param("validate"))?
+        let builder = self.geetest_get("refresh.php").query(&params);
+        self.record_request();
+        let res = send_text(builder, self.traffic())?;
+
+        parse_args(&parse_jsonp(&res, &callback)?)
+    }
+}
+
+/// ### 从 get.php / refresh.php 的响应中取出计算key用到的参数
+/// - (challenge, fullbg, bg, slice)，图片地址补全为完整 URL
+fn parse_args(res: &Value) -> Result<(String, String, String, String)> {
+    let static_server = res
+        .get("static_servers")
+        .ok_or_else(|| missing_param("static_servers"))?
+        .as_array()
+        .ok_or_else(|| missing_param("static_servers"))?
+        .get(0)
+        .ok_or_else(|| other_without_source("static_servers里面咋没东西啊"))?
+        .as_str()
+        .ok_or_else(|| other_without_source("static_servers里面咋没东西啊"))?;
+    let image_url = |name: &str| -> Result<String> {
+        Ok(format!(
+            "https://{}{}",
+            static_server,
+            res.get(name)
+                .ok_or_else(|| missing_param(name))?
                 .as_str()
-                .ok_or_else(|| missing_param("validate"))?
-                .to_string(),
+                .ok_or_else(|| missing_param(name))?
         ))
+    };
+
+    Ok((
+        res.get("challenge")
+            .ok_or_else(|| missing_param("challenge"))?
+            .as_str()
+            .ok_or_else(|| missing_param("challenge"))?
+            .to_string(),
+        image_url("fullbg")?,
+        image_url("bg")?,
+        image_url("slice")?,
+    ))
+}
+
+fn verify_params<'a>(
+    gt: &'a str,
+    challenge: &'a str,
+    callback: &'a str,
+    w: Option<&'a str>,
+) -> HashMap<&'a str, &'a str> {
+    let mut params = HashMap::from([
+        ("gt", gt),
+        ("challenge", challenge),
+        ("callback", callback), // 使用动态回调
+    ]);
+    if let Some(w) = w {
+        params.insert("w", w);
     }
+    params
+}
 
-    fn refresh(&self, _gt: &str, _challenge: &str) -> Result<Self::ArgsType> {
-        todo!("{}", "暂时不写")
+fn parse_verify(res: &Value, challenge: &str) -> Result<(String, String, VerifyPayload)> {
+    let message = res
+        .get("message")
+        .ok_or_else(|| missing_param("message"))?
+        .as_str()
+        .ok_or_else(|| missing_param("message"))?
+        .to_string();
+    // 验证未通过时 success 为 0，不带 validate
+    if res.get("success").and_then(Value::as_i64) == Some(0) {
+        return Err(rejected(&format!("极验验证未通过: {}", message)));
     }
+    let validate = res
+        .get("validate")
+        .ok_or_else(|| missing_param("validate"))?
+        .as_str()
+        .ok_or_else(|| missing_param("validate"))?
+        .to_string();
+    let payload = verify_payload(res, challenge, &validate);
+    Ok((message, validate, payload))
 }
 
 impl GenerateW for Slide {
     fn calculate_key(&mut self, args: Self::ArgsType) -> Result<String> {
         let (_, _, bg, slice) = args;
-        let bg_img = self.download_img(bg.as_str())?;
-        let slice_img = self.download_img(slice.as_str())?;
-        let slice_img = image::load_from_memory(&slice_img).map_err(|e| other("内部错误", e))?;
-        let bg_img = image::load_from_memory(&bg_img).map_err(|e| other("图片解析错误", e))?;
-        let mut new_bg_img = image::ImageBuffer::new(260, 160);
-        let offset = [
-            39, 38, 48, 49, 41, 40, 46, 47, 35, 34, 50, 51, 33, 32, 28, 29, 27, 26, 36, 37, 31, 30,
-            44, 45, 43, 42, 12, 13, 23, 22, 14, 15, 21, 20, 8, 9, 25, 24, 6, 7, 3, 2, 0, 1, 11, 10,
-            4, 5, 19, 18, 16, 17,
-        ];
-        let (w_sep, h_sep) = (10u32, 80u32);
-        for idx in 0..52 {
-            let x = (offset[idx] % 26 * 12) as u32;
-            let y = if offset[idx] > 25 { h_sep } else { 0 };
-            let new_x = (idx % 26 * 10) as u32;
-            let new_y = if idx > 25 { h_sep } else { 0 };
-
-            let pi = bg_img.crop_imm(x, y, w_sep, h_sep);
-            new_bg_img.copy_from(&pi, new_x, new_y).unwrap();
-        }
-        let new_bg_img = DynamicImage::ImageRgba8(new_bg_img);
-        let res_x = Slide0::run(&slice_img, &new_bg_img)
-            .map_err(|_| other_without_source("滑块识别内部错误"))?
-            .x1;
-        Ok(res_x.to_string())
+        let bg_img = self.fetch_img(bg.as_str())?;
+        let slice_img = self.fetch_img(slice.as_str())?;
+        Ok(detect_gap(&bg_img, &slice_img)?.gap.to_string())
     }
 
     fn generate_w(&self, key: &str, gt: &str, challenge: &str, c: &[u8], s: &str) -> Result<String> {
-        Ok(slide_calculate(
-            key.parse()
-                .map_err(|e| other("滑动距离不是整数类型", e))?,
-            gt,
-            challenge,
-            c,
-            s,
-        ))
+        w::generate_w(self.verify_type, key, gt, challenge, c, s, &self.w_options)
+    }
+
+    fn generate_w_debug(&self, key: &str, gt: &str, challenge: &str, c: &[u8], s: &str) -> Result<(String, WDebug)> {
+        w::generate_w_debug(self.verify_type, key, gt, challenge, c, s, &self.w_options)
     }
 }
 
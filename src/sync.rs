@@ -0,0 +1,37 @@
+// sync.rs
+
+use std::sync::{Mutex, MutexGuard};
+
+/// ### 获取锁，遇到毒化时自动恢复
+/// - 持锁线程 panic 后 Mutex 会被毒化，之后每次 lock 都返回错误直到重启
+/// - 这里取出内部数据继续使用并清除毒化标记，让服务自愈
+pub fn lock_or_recover<'a, T>(mutex: &'a Mutex<T>, name: &str) -> MutexGuard<'a, T> {
+    mutex.lock().unwrap_or_else(|poisoned| {
+        tracing::warn!("{} 的 Mutex 已被毒化，恢复后继续使用", name);
+        mutex.clear_poison();
+        poisoned.into_inner()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn recovers_poisoned_mutex() {
+        let mutex = Arc::new(Mutex::new(1));
+        let poisoner = Arc::clone(&mutex);
+        let _ = std::thread::spawn(move || {
+            let _guard = poisoner.lock().unwrap();
+            panic!("持锁时 panic");
+        })
+        .join();
+        assert!(mutex.is_poisoned());
+
+        *lock_or_recover(mutex.as_ref(), "测试") += 1;
+        // 毒化标记已清除，之后普通的 lock 也能成功
+        assert!(!mutex.is_poisoned());
+        assert_eq!(*mutex.lock().unwrap(), 2);
+    }
+}
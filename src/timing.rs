@@ -0,0 +1,55 @@
+// timing.rs
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// ### 单次调用的耗时拆分
+/// - network_ms: 与极验之间的往返（含代理），回放时为读取录制的耗时
+/// - compute_ms: 本地计算，即总耗时减去网络耗时，包括解析响应与生成 w
+/// - total_ms: 总耗时
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct Timings {
+    pub network_ms: u64,
+    pub compute_ms: u64,
+    pub total_ms: u64,
+}
+
+/// ### 计时器
+/// - 创建时开始计时，网络请求包在 `network` 中单独累计
+pub(crate) struct Stopwatch {
+    started: Instant,
+    network: Duration,
+}
+
+impl Stopwatch {
+    pub(crate) fn start() -> Self {
+        Self { started: Instant::now(), network: Duration::ZERO }
+    }
+
+    /// 执行一次网络请求并累计其耗时
+    pub(crate) fn network<T>(&mut self, f: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let res = f();
+        self.network += started.elapsed();
+        res
+    }
+
+    /// 与 `network` 相同，用于异步请求
+    #[cfg(feature = "async-client")]
+    pub(crate) async fn network_async<T>(&mut self, f: impl std::future::Future<Output = T>) -> T {
+        let started = Instant::now();
+        let res = f.await;
+        self.network += started.elapsed();
+        res
+    }
+
+    pub(crate) fn finish(self) -> Timings {
+        let total = self.started.elapsed();
+        Timings {
+            network_ms: self.network.as_millis() as u64,
+            compute_ms: total.saturating_sub(self.network).as_millis() as u64,
+            total_ms: total.as_millis() as u64,
+        }
+    }
+}
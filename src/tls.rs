@@ -0,0 +1,34 @@
+// tls.rs
+
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use axum_server::Handle;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::path::Path;
+
+/// ### 读取 PEM 格式的证书链与私钥
+/// - 在监听前调用，证书无效时启动即失败，而不是等到第一次握手
+pub(crate) async fn load(cert: &Path, key: &Path) -> io::Result<RustlsConfig> {
+    RustlsConfig::from_pem_file(cert, key).await
+}
+
+/// ### 以 HTTPS 提供服务
+/// - 行为与 `axum::serve(..).with_graceful_shutdown(..)` 一致: 收到信号后停止接受新连接，
+///   等待已有连接处理完毕后返回，超时强制退出仍由调用方负责
+pub(crate) async fn serve<F>(addr: SocketAddr, config: RustlsConfig, app: Router, signal: F) -> io::Result<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let handle = Handle::new();
+    let shutdown = handle.clone();
+    tokio::spawn(async move {
+        signal.await;
+        shutdown.graceful_shutdown(None);
+    });
+    axum_server::bind_rustls(addr, config)
+        .handle(handle)
+        .serve(app.into_make_service())
+        .await
+}
@@ -0,0 +1,86 @@
+// unix_socket.rs
+
+use axum::extract::Request;
+use axum::Router;
+use hyper::body::Incoming;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto::Builder;
+use std::future::Future;
+use std::io;
+use std::path::Path;
+use tokio::net::UnixListener;
+use tokio::sync::watch;
+use tower::ServiceExt;
+
+/// ### 绑定 Unix 套接字
+/// - 上次异常退出残留的套接字文件会先被删除
+pub(crate) fn bind(path: &Path) -> io::Result<UnixListener> {
+    match std::fs::remove_file(path) {
+        Ok(()) => tracing::warn!("已删除残留的套接字文件 {}", path.display()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    UnixListener::bind(path)
+}
+
+/// ### 在 Unix 套接字上提供服务
+/// - axum 0.7 的 `serve` 只支持 TcpListener，这里用 hyper-util 逐个连接处理
+/// - 行为与 `axum::serve(..).with_graceful_shutdown(..)` 一致: 收到信号后停止接受新连接，
+///   等待已有连接处理完毕后返回
+pub(crate) async fn serve<F>(listener: UnixListener, app: Router, signal: F) -> io::Result<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    // signal_rx 被丢弃即表示开始停机；close_rx 全部被丢弃表示连接都已结束
+    let (signal_tx, signal_rx) = watch::channel(());
+    tokio::spawn(async move {
+        signal.await;
+        drop(signal_rx);
+    });
+    let (close_tx, close_rx) = watch::channel(());
+
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    tracing::warn!("接受 Unix 套接字连接失败: {}", e);
+                    continue;
+                }
+            },
+            _ = signal_tx.closed() => break,
+        };
+
+        let app = app.clone();
+        let service = hyper::service::service_fn(move |req: Request<Incoming>| app.clone().oneshot(req));
+        let signal_tx = signal_tx.clone();
+        let close_rx = close_rx.clone();
+        tokio::spawn(async move {
+            let builder = Builder::new(TokioExecutor::new());
+            // 支持 /ws/solve 的 WebSocket 升级
+            let conn = builder.serve_connection_with_upgrades(TokioIo::new(stream), service);
+            tokio::pin!(conn);
+            let mut shutting_down = false;
+            loop {
+                tokio::select! {
+                    result = conn.as_mut() => {
+                        if let Err(e) = result {
+                            tracing::debug!("Unix 套接字连接错误: {}", e);
+                        }
+                        break;
+                    }
+                    _ = signal_tx.closed(), if !shutting_down => {
+                        shutting_down = true;
+                        conn.as_mut().graceful_shutdown();
+                    }
+                }
+            }
+            drop(close_rx);
+        });
+    }
+
+    drop(close_rx);
+    drop(listener);
+    close_tx.closed().await;
+    Ok(())
+}
@@ -0,0 +1,159 @@
+// verify_cache.rs
+
+use crate::sync::lock_or_recover;
+use axum::{
+    body::{to_bytes, Body, HttpBody},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use lru::LruCache;
+use serde_json::Value;
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// ### 验证结果缓存
+/// - 相同的 (gt, challenge, w) 在短时间内重复验证时极验返回相同结果，重试时直接复用，不再请求极验
+/// - 会话、代理或 Cookie 不同的请求分开缓存，不会拿到以其他身份验证得到的结果
+/// - 只缓存成功的验证，失败的响应原样返回且不影响已缓存的结果
+/// - 命中时在 data 中带上 `cached: true`
+/// - 条数有上限，超出后淘汰最久未使用的；过期的条目在下次访问时丢弃
+/// - 响应体超过 max_body（同请求体上限 GT_MAX_BODY_BYTES）时原样返回，不缓存
+pub(crate) struct VerifyCache {
+    ttl: Duration,
+    max_body: usize,
+    entries: Mutex<LruCache<String, (Instant, Value)>>,
+}
+
+impl VerifyCache {
+    pub(crate) fn new(capacity: NonZeroUsize, ttl: Duration, max_body: usize) -> Self {
+        Self { ttl, max_body, entries: Mutex::new(LruCache::new(capacity)) }
+    }
+
+    /// ### 缓存键
+    /// - 各部分以不会出现在参数中的 `\0` 分隔；include_full 不同时响应形状不同，分开缓存
+    /// - `origin` 为调用方的会话、代理与 Cookie，由调用方拼好
+    pub(crate) fn key(kind: &str, gt: &str, challenge: &str, w: &str, include_full: bool, origin: &str) -> String {
+        format!("{}\0{}\0{}\0{}\0{}\0{}", kind, gt, challenge, w, include_full, origin)
+    }
+
+    /// ### 取缓存或执行验证
+    /// #### 参数
+    /// - `key`: 由 `VerifyCache::key` 生成
+    /// - `verify`: 完整的处理过程，未命中时才会执行
+    pub(crate) async fn run<F>(&self, key: String, verify: F) -> Response
+    where
+        F: Future<Output = Response>,
+    {
+        if let Some(mut body) = self.get(&key) {
+            tracing::info!("相同的验证已有结果，直接返回缓存");
+            if let Some(data) = body.get_mut("data").and_then(Value::as_object_mut) {
+                data.insert("cached".to_string(), Value::Bool(true));
+            }
+            return Json(body).into_response();
+        }
+        let response = verify.await;
+        if response.status() != StatusCode::OK {
+            return response;
+        }
+        let fits = response.body().size_hint().upper().is_some_and(|len| len <= self.max_body as u64);
+        if !fits {
+            return response;
+        }
+        let (parts, body) = response.into_parts();
+        let bytes = match to_bytes(body, self.max_body).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                tracing::error!("读取验证响应失败: {}", e);
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        };
+        if let Ok(body) = serde_json::from_slice::<Value>(&bytes) {
+            if body["success"] == true && body["data"].is_object() {
+                lock_or_recover(&self.entries, "验证结果缓存").put(key, (Instant::now(), body));
+            }
+        }
+        Response::from_parts(parts, Body::from(bytes))
+    }
+
+    fn get(&self, key: &str) -> Option<Value> {
+        let mut entries = lock_or_recover(&self.entries, "验证结果缓存");
+        let (at, body) = entries.get(key)?;
+        if at.elapsed() < self.ttl {
+            return Some(body.clone());
+        }
+        entries.pop(key);
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    async fn json_body(response: Response) -> Value {
+        serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn successful_verify_is_reused_until_it_expires() {
+        let cache = VerifyCache::new(NonZeroUsize::new(8).unwrap(), Duration::from_millis(50), 4096);
+        let verifies = Arc::new(AtomicUsize::new(0));
+        let key = VerifyCache::key("click", "gt", "challenge", "w", false, "session");
+        let verify = || {
+            let verifies = Arc::clone(&verifies);
+            async move {
+                verifies.fetch_add(1, Ordering::SeqCst);
+                Json(json!({ "success": true, "data": { "first": "ok", "second": "validate" } })).into_response()
+            }
+        };
+
+        let fresh = json_body(cache.run(key.clone(), verify()).await).await;
+        assert!(fresh["data"].get("cached").is_none());
+        let cached = json_body(cache.run(key.clone(), verify()).await).await;
+        assert_eq!(cached["data"]["cached"], true);
+        assert_eq!(cached["data"]["second"], "validate");
+        assert_eq!(verifies.load(Ordering::SeqCst), 1);
+
+        // 其他会话、代理或 Cookie 的相同验证不复用
+        let other = VerifyCache::key("click", "gt", "challenge", "w", false, "other");
+        assert!(json_body(cache.run(other, verify()).await).await["data"].get("cached").is_none());
+        assert_eq!(verifies.load(Ordering::SeqCst), 2);
+
+        tokio::time::sleep(Duration::from_millis(80)).await;
+        cache.run(key, verify()).await;
+        assert_eq!(verifies.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn errors_are_not_cached() {
+        let cache = VerifyCache::new(NonZeroUsize::new(8).unwrap(), Duration::from_secs(60), 4096);
+        let key = VerifyCache::key("slide", "gt", "challenge", "w", false, "session");
+        let failed = cache
+            .run(key.clone(), async {
+                (StatusCode::BAD_REQUEST, Json(json!({ "success": false, "error_code": "rejected" }))).into_response()
+            })
+            .await;
+        assert_eq!(failed.status(), StatusCode::BAD_REQUEST);
+        assert!(cache.get(&key).is_none());
+    }
+
+    #[tokio::test]
+    async fn oversized_responses_pass_through_uncached() {
+        let cache = VerifyCache::new(NonZeroUsize::new(8).unwrap(), Duration::from_secs(60), 64);
+        let key = VerifyCache::key("slide", "gt", "challenge", "w", false, "session");
+        let validate = "v".repeat(128);
+        let body = json_body(
+            cache
+                .run(key.clone(), async { Json(json!({ "success": true, "data": { "validate": validate } })).into_response() })
+                .await,
+        )
+        .await;
+        assert_eq!(body["data"]["validate"].as_str().map(str::len), Some(128));
+        assert!(cache.get(&key).is_none());
+    }
+}
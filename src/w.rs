@@ -1,11 +1,58 @@
-use std::collections::HashSet;
-use rand::{random, thread_rng, Rng};
+use std::collections::{BTreeMap, HashSet};
+use std::time::Instant;
+use once_cell::sync::Lazy;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use rsa::{BigUint, RsaPublicKey, Pkcs1v15Encrypt};
-use rand::rngs::OsRng;
+use crate::abstraction::VerifyType;
+use crate::error::{invalid_input, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use serde_json::{json};
-use soft_aes::aes::aes_enc_cbc;
+use soft_aes::aes::{aes_dec_cbc, aes_enc_cbc};
 use md5;
 
+/// ### w 算法版本
+/// - 加密流程（RSA/AES、轨迹编码等）变化时递增，通过 /version 暴露，用于区分同时运行的多个版本
+/// - 为 W_ALGORITHMS 中最新的版本，请求未指定 algo_version 时使用
+pub const W_ALGORITHM_VERSION: u32 = 1;
+
+/// ### 一个版本的 w 算法实现
+/// - 新版本追加到 W_ALGORITHMS 末尾并同步递增 W_ALGORITHM_VERSION，旧版本保留到 A/B 对照结束
+pub struct WAlgorithm {
+    pub version: u32,
+    click: fn(&str, &str, &str, &mut StdRng, &mut Option<WDebug>) -> String,
+    #[allow(clippy::type_complexity)]
+    slide: fn(i32, &str, &str, &[u8], &str, &TrackOptions, &mut StdRng, &mut Option<WDebug>) -> Result<String>,
+}
+
+/// 可用的 w 算法，按版本升序
+const W_ALGORITHMS: &[WAlgorithm] = &[WAlgorithm {
+    version: 1,
+    click: click_calculate_traced,
+    slide: slide_calculate_traced,
+}];
+
+/// 可用的 w 算法版本，按升序排列
+pub fn w_algorithm_versions() -> Vec<u32> {
+    W_ALGORITHMS.iter().map(|algorithm| algorithm.version).collect()
+}
+
+/// ### 按版本取出 w 算法
+/// - None 时为最新版本 W_ALGORITHM_VERSION
+/// - 未知版本返回 InvalidInput
+pub fn w_algorithm(version: Option<u32>) -> Result<&'static WAlgorithm> {
+    let version = version.unwrap_or(W_ALGORITHM_VERSION);
+    W_ALGORITHMS
+        .iter()
+        .find(|algorithm| algorithm.version == version)
+        .ok_or_else(|| invalid_input(&format!("未知的 algo_version {}，可用版本: {:?}", version, w_algorithm_versions())))
+}
+
+/// ### w 的编码
+/// - 前半为 AES 密文按 BASE64_TABLE（末两位为 `()`）编码，后半为 RSA 加密会话密钥的 256 位 hex
+pub const W_FORMAT: &str = "base64+hex";
+
 const RSA_N: &str = "00C1E3934D1614465B33053E7F48EE4EC87B14B95EF88947713D25EECBFF7E74C7977D02DC1D9451F79DD5D1C10C29ACB6A9B4D6FB7D0A0279B6719E1772565F09AF627715919221AEF91899CAE08C0D686D748B20A3603BE2318CA6BC2B59706592A9219D0BF05C9F65023A21D2330807252AE0066D59CEEFA5F2748EA80BAB81";
 const RSA_E: &str = "010001";
 const AES_KEY: &str = "1234567890123456";
@@ -79,7 +126,59 @@ pub fn base64(input: &[u8]) -> String {
     format!("{}{}", result, padding)
 }
 
-fn rsa_encrypt(data: &str) -> String {
+/// get_int_by_mask 的逆运算: 把 value 的各位依次放回 mask 中为 1 的位置
+#[inline(always)]
+fn set_int_by_mask(value: i32, mask: i32) -> i32 {
+    let mut idx = mask.count_ones() as i32;
+    let mut res = 0;
+    for bit in (0..24).rev() {
+        if choose_bit(mask, bit) == 1 {
+            idx -= 1;
+            res |= choose_bit(value, idx) << bit;
+        }
+    }
+    res
+}
+
+/// ### base64 的逆运算
+/// #### 返回值
+/// - 含有码表之外的字符或长度不对时返回 None
+pub fn base64_decode(input: &str) -> Option<Vec<u8>> {
+    let (body, padding) = match input.strip_suffix("..") {
+        Some(body) => (body, 2),
+        None => match input.strip_suffix('.') {
+            Some(body) => (body, 1),
+            None => (input, 0),
+        },
+    };
+    let values = body
+        .bytes()
+        .map(|b| BASE64_TABLE.iter().position(|&t| t == b).map(|v| v as i32))
+        .collect::<Option<Vec<i32>>>()?;
+    // 末尾不足 3 字节的一组: 2 字节编码为 3 个字符，1 字节编码为 2 个字符
+    let tail = match padding {
+        0 => 0,
+        padding => 4 - padding,
+    };
+    if values.len() < tail || (values.len() - tail) % 4 != 0 {
+        return None;
+    }
+    let mut output = Vec::with_capacity(values.len() / 4 * 3 + 2);
+    for group in values.chunks(4) {
+        let c = [MASK1, MASK2, MASK3, MASK4]
+            .iter()
+            .zip(group)
+            .fold(0, |c, (&mask, &value)| c | set_int_by_mask(value, mask));
+        let bytes = [(c >> 16) as u8, (c >> 8) as u8, c as u8];
+        output.extend_from_slice(&bytes[..group.len() - 1]);
+    }
+    Some(output)
+}
+
+/// ### 极验的 RSA 公钥
+/// - 解析（十六进制解码、大数构造与校验）是 encrypt 中唯一与输入无关的开销，只在第一次使用时进行
+/// - RSA 填充是随机的，密文本身不能缓存
+static RSA_PUBLIC_KEY: Lazy<RsaPublicKey> = Lazy::new(|| {
     let n_bytes = hex::decode(RSA_N).expect("Invalid hex for modulus (n)");
     let e_bytes = hex::decode(RSA_E).expect("Invalid hex for exponent (e)");
 
@@ -87,13 +186,14 @@ fn rsa_encrypt(data: &str) -> String {
     let e = BigUint::from_bytes_be(&e_bytes);
 
     // 构建 RSA 公钥
-    let pub_key = RsaPublicKey::new(n, e).expect("Invalid RSA public key");
+    RsaPublicKey::new(n, e).expect("Invalid RSA public key")
+});
 
+fn rsa_encrypt(data: &str, rng: &mut StdRng) -> String {
     // 使用 PKCS#1 v1.5 填充进行加密
     let padding = Pkcs1v15Encrypt;
-    let mut rng = OsRng;
-    let encrypted_data = pub_key
-        .encrypt(&mut rng, padding, data.as_bytes())
+    let encrypted_data = RSA_PUBLIC_KEY
+        .encrypt(rng, padding, data.as_bytes())
         .expect("Encryption failed");
 
     // 转换为十六进制字符串
@@ -105,15 +205,49 @@ fn aes_encrypt(data: &str) -> Vec<u8> {
     encrypted
 }
 
-fn encrypt(json_str: &str) -> String{
-    let u = rsa_encrypt(AES_KEY);
-    let h = aes_encrypt(json_str);
-    let p = base64(h.as_ref());
+/// ### 生成w过程中的中间值
+/// - 仅在调试模式下收集，正常路径不做任何记录
+#[derive(Debug, Default, Serialize, JsonSchema)]
+pub struct WDebug {
+    /// 关键参数（点选坐标串 / 滑块距离）的长度
+    pub key_len: usize,
+    /// passtime
+    pub pass_time: i64,
+    /// 滑块轨迹点 [x, y, t]，点选为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub track: Option<Vec<Vec<i32>>>,
+    /// 各步骤耗时（微秒）
+    pub timings_us: BTreeMap<&'static str, u64>,
+}
+
+/// 调试模式下记录 step 的耗时，否则直接执行
+fn timed<T>(trace: &mut Option<WDebug>, step: &'static str, f: impl FnOnce() -> T) -> T {
+    match trace {
+        Some(debug) => {
+            let start = Instant::now();
+            let res = f();
+            debug.timings_us.insert(step, start.elapsed().as_micros() as u64);
+            res
+        }
+        None => f(),
+    }
+}
+
+fn encrypt(json_str: &str, rng: &mut StdRng, trace: &mut Option<WDebug>) -> String{
+    let u = timed(trace, "rsa", || rsa_encrypt(AES_KEY, rng));
+    let h = timed(trace, "aes", || aes_encrypt(json_str));
+    let p = timed(trace, "base64", || base64(h.as_ref()));
     format!("{}{}", p, u)
 }
 
-pub(crate) fn click_calculate(key: &str, gt: &str, challenge: &str) -> String {
-    let pass_time = (random::<f32>() * 700f32 + 1300f32) as usize;
+fn click_calculate_traced(
+    key: &str,
+    gt: &str,
+    challenge: &str,
+    rng: &mut StdRng,
+    trace: &mut Option<WDebug>,
+) -> String {
+    let pass_time = (rng.gen::<f32>() * 700f32 + 1300f32) as usize;
     let m5 = md5::compute(format!("{}{}{}", gt, &challenge[..challenge.len()-2].to_string(), pass_time));
     let rp = hex::encode(m5.to_vec());
 
@@ -169,16 +303,97 @@ pub(crate) fn click_calculate(key: &str, gt: &str, challenge: &str) -> String {
         "rp": rp,
     });
 
-    encrypt(dic.to_string().as_str())
+    if let Some(debug) = trace {
+        debug.pass_time = pass_time as i64;
+    }
+    encrypt(dic.to_string().as_str(), rng, trace)
+}
+
+/// ### 滑块轨迹的缓动曲线
+/// - 输入为进度 [0, 1]，输出为已滑动距离占比
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Easing {
+    /// 指数减速，之前固定使用的曲线
+    #[default]
+    ExpoOut,
+    QuadOut,
+    CubicOut,
+    SineOut,
+}
+
+impl Easing {
+    fn apply(self, sep: f64) -> f64 {
+        match self {
+            Easing::ExpoOut => 1.0 - 2f64.powf(-10.0 * sep),
+            Easing::QuadOut => 1.0 - (1.0 - sep).powi(2),
+            Easing::CubicOut => 1.0 - (1.0 - sep).powi(3),
+            Easing::SineOut => (sep * std::f64::consts::FRAC_PI_2).sin(),
+        }
+    }
 }
 
-fn get_slide_track(distance: i32) -> Vec<Vec<i32>> {
-    if distance < 0 {
-        panic!("distance必须大于等于0");
+/// 轨迹点数上限
+pub const MAX_TRACK_POINTS: u32 = 1000;
+/// 滑动总时长上限（毫秒）
+pub const MAX_TRACK_DURATION_MS: u32 = 60_000;
+
+/// ### 滑块轨迹参数
+/// - 均可省略，省略时生成的轨迹与之前一致
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema)]
+pub struct TrackOptions {
+    /// 滑动总时长范围（毫秒）[最小, 最大]，省略时每个点间隔 10~20ms，最大 60000
+    pub duration_ms: Option<[u32; 2]>,
+    /// 轨迹点数，省略时为 30 + 距离 / 2，最大 1000
+    pub points: Option<u32>,
+    /// 缓动曲线
+    #[serde(default)]
+    pub easing: Easing,
+}
+
+impl TrackOptions {
+    /// ### 检查轨迹参数
+    /// - 点数超过 MAX_TRACK_POINTS 或时长超过 MAX_TRACK_DURATION_MS 时返回 InvalidInput
+    /// - 供接口在生成前拒绝请求；生成轨迹时仍会截断到上限
+    pub fn validate(&self) -> Result<()> {
+        if let Some(points) = self.points.filter(|&points| points > MAX_TRACK_POINTS) {
+            return Err(invalid_input(&format!("轨迹点数 {} 超过上限 {}", points, MAX_TRACK_POINTS)));
+        }
+        if let Some(duration) = self.duration_ms.filter(|[a, b]| (*a).max(*b) > MAX_TRACK_DURATION_MS) {
+            return Err(invalid_input(&format!(
+                "滑动时长 {:?} 毫秒超过上限 {}",
+                duration, MAX_TRACK_DURATION_MS
+            )));
+        }
+        Ok(())
     }
+}
+
+/// ### 生成 w 的可选参数
+#[derive(Clone, Debug, Default)]
+pub struct WOptions {
+    /// 滑块轨迹参数，点选忽略
+    pub track: TrackOptions,
+    /// 随机数种子: 设置后相同输入生成的 w 逐字节一致（轨迹、passtime、RSA 填充等），用于回归测试
+    pub seed: Option<u64>,
+    /// w 算法版本，省略时使用最新版本，见 W_ALGORITHMS
+    pub algo_version: Option<u32>,
+}
+
+impl WOptions {
+    /// 未设置种子时从系统熵源初始化，与之前一样每次都不同
+    fn rng(&self) -> StdRng {
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        }
+    }
+}
+
+fn get_slide_track(distance: i32, options: &TrackOptions, rng: &mut StdRng) -> Result<Vec<Vec<i32>>> {
+    check_slide_distance(distance)?;
 
     let mut slide_track = Vec::new();
-    let mut rng = thread_rng();
 
     // 初始化轨迹列表
     let x1 = rng.gen_range(-50..=-10); // 生成-50到-10之间的整数
@@ -187,7 +402,21 @@ fn get_slide_track(distance: i32) -> Vec<Vec<i32>> {
     slide_track.push(vec![0, 0, 0]);
 
     // 计算记录次数
-    let count = 30 + (distance / 2);
+    let count = match options.points {
+        Some(points) => points.clamp(2, MAX_TRACK_POINTS) as i32,
+        None => 30 + (distance / 2),
+    };
+
+    // 每个点的时间间隔: 指定总时长时围绕平均间隔上下浮动一半
+    let step_range = match options.duration_ms {
+        Some([a, b]) => {
+            let (a, b) = (a.min(MAX_TRACK_DURATION_MS), b.min(MAX_TRACK_DURATION_MS));
+            let total = rng.gen_range(a.min(b)..=a.max(b)) as i32;
+            let avg = (total / count).max(2);
+            (avg / 2)..=(avg + avg / 2)
+        }
+        None => 10..=20,
+    };
 
     // 初始化滑动时间
     let mut t = rng.gen_range(50..=100);
@@ -201,12 +430,12 @@ fn get_slide_track(distance: i32) -> Vec<Vec<i32>> {
         let x = if sep == 1.0 {
             distance as f64
         } else {
-            (1f64 - 2f64.powf(-10.0 * sep)) * distance as f64
+            options.easing.apply(sep) * distance as f64
         };
         let x_rounded = x.round() as i32;
 
         // 增加时间
-        t += rng.gen_range(10..=20);
+        t += rng.gen_range(step_range.clone());
 
         if x_rounded == _x {
             continue;
@@ -221,7 +450,7 @@ fn get_slide_track(distance: i32) -> Vec<Vec<i32>> {
         slide_track.push(last.clone());
     }
 
-    slide_track
+    Ok(slide_track)
 }
 
 fn track_encrypt(track: &Vec<Vec<i32>>) -> String {
@@ -327,7 +556,16 @@ fn track_encrypt(track: &Vec<Vec<i32>>) -> String {
     format!("{}!!{}!!{}", r, i, o)
 }
 
-fn final_encrypt(t: String, e: &[u8], n: String) -> String {
+/// ### 解析 s
+/// - s 须为非空、偶数长度的十六进制串，否则返回 InvalidInput
+fn parse_s(s: &str) -> Result<Vec<u8>> {
+    match hex::decode(s) {
+        Ok(bytes) if !bytes.is_empty() => Ok(bytes),
+        _ => Err(invalid_input(&format!("s 不是非空、偶数长度的十六进制串: {:?}", s))),
+    }
+}
+
+fn final_encrypt(t: String, e: &[u8], n: &[u8]) -> String {
     if e.len() < 5 || n.is_empty() {
         return t;
     }
@@ -337,19 +575,14 @@ fn final_encrypt(t: String, e: &[u8], n: String) -> String {
     let m = e[4];
 
     let original_len = t.len(); // 固定使用原始长度
-    let mut i = 0;
     let mut o = t.clone();
 
-    while i <= n.len() - 2 {
-        let r = &n[i..i + 2];
-        i += 2;
-
-        let c = u8::from_str_radix(r, 16).unwrap();
+    for &c in n {
         let u = char::from(c);
 
         // 基于原始长度计算插入位置
         let ll = (s as u64 * c as u64 * c as u64 + a as u64 * c as u64 + m as u64) % original_len as u64;
-        let ll = ll as usize;
+        let ll = next_char_boundary(&o, ll as usize);
 
         o.insert(ll, u);
     }
@@ -357,10 +590,20 @@ fn final_encrypt(t: String, e: &[u8], n: String) -> String {
     o
 }
 
-fn user_response(key: i32, challenge: &str) -> String {
+/// 大于 0x7f 的字节插入后占两个字节，落在其中间时顺延到下一个字符边界
+fn next_char_boundary(s: &str, mut idx: usize) -> usize {
+    while !s.is_char_boundary(idx) {
+        idx += 1;
+    }
+    idx
+}
+
+fn user_response(key: i32, challenge: &str) -> Result<String> {
     // 处理最后两个字符
+    if challenge.len() < 2 || !challenge.is_ascii() {
+        return Err(invalid_input(&format!("challenge 须为至少两个字符的 ASCII 串: {:?}", challenge)));
+    }
     let chars_e: Vec<char> = challenge.chars().collect();
-    assert!(chars_e.len() >= 2, "输入字符串 e 必须至少包含两个字符");
     let n_chars = &chars_e[chars_e.len() - 2..];
 
     // 计算 r 数组
@@ -374,7 +617,9 @@ fn user_response(key: i32, challenge: &str) -> String {
     let n = 36 * r[0] + r[1];
 
     // 计算初始值 a
-    let a = key + n;
+    let a = key
+        .checked_add(n)
+        .ok_or_else(|| invalid_input(&format!("滑动距离 {} 与 challenge 末两位相加溢出", key)))?;
 
     // 初始化数据结构
     let mut underscores = vec![vec![]; 5]; // 五元组数组
@@ -400,44 +645,55 @@ fn user_response(key: i32, challenge: &str) -> String {
     let mut weights = vec![1, 2, 5, 10, 50]; // 权重数组
 
     while f > 0 {
-        // 处理下标越界情况（理论上不会出现）
-        if d >= weights.len() {
-            panic!("权重数组越界，请检查输入参数有效性");
-        }
-
         if f >= weights[d] {
-            // 安全访问第一个元素
-            if let Some(&char) = underscores[d].get(0) {
-                result.push(char);
-                f -= weights[d];
-            } else {
-                panic!("五元组数组 {} 号位置无可用字符，请确保输入字符串包含足够多的唯一字符", d);
-            }
+            // challenge 中不同的字符不足 5 个时对应位置为空
+            let Some(&char) = underscores[d].first() else {
+                return Err(invalid_input(&format!("challenge 中不同的字符不足，无法生成 userresponse: {:?}", challenge)));
+            };
+            result.push(char);
+            f -= weights[d];
         } else {
-            // 移除当前权重并下移指针
+            // 移除当前权重并下移指针；权重 1 总能满足 f > 0，d 不会越过 0
             underscores.remove(d);
             weights.remove(d);
-            if d > 0 {
-                d -= 1;
-            } else {
-                panic!("权重数组耗尽，无法继续处理");
-            }
+            d -= 1;
         }
     }
 
-    result
+    Ok(result)
 }
 
 
-pub fn slide_calculate(key: i32, gt: &str, challenge: &str, c: &[u8], s: &str) -> String {
-    let track = get_slide_track(key);
+/// ### 生成滑块的 w
+/// - 滑动距离超出 0..=MAX_SLIDE_DISTANCE 时返回 InvalidInput
+/// - s 不是非空、偶数长度的十六进制串，或 challenge 无法生成 userresponse 时同样返回 InvalidInput
+pub fn slide_calculate(key: i32, gt: &str, challenge: &str, c: &[u8], s: &str, options: &WOptions) -> Result<String> {
+    slide_calculate_traced(key, gt, challenge, c, s, &options.track, &mut options.rng(), &mut None)
+}
+
+fn slide_calculate_traced(
+    key: i32,
+    gt: &str,
+    challenge: &str,
+    c: &[u8],
+    s: &str,
+    track_options: &TrackOptions,
+    rng: &mut StdRng,
+    trace: &mut Option<WDebug>,
+) -> Result<String> {
+    let s = parse_s(s)?;
+    let track = timed(trace, "track", || get_slide_track(key, track_options, rng))?;
     let pass_time = track.last().unwrap()[2];
-    let aa = {
+    let aa = timed(trace, "track_encrypt", || {
         let encrypted_track = track_encrypt(&track);
-        final_encrypt(encrypted_track, c, s.to_string())
-    };
+        final_encrypt(encrypted_track, c, &s)
+    });
+    if let Some(debug) = trace {
+        debug.pass_time = pass_time as i64;
+        debug.track = Some(track.clone());
+    }
 
-    let user_response = user_response(key, challenge);
+    let user_response = user_response(key, challenge)?;
 
     let m5 = md5::compute(format!("{}{}{}", gt, &challenge[..challenge.len() - 2], pass_time));
     let rp = hex::encode(m5.to_vec());
@@ -446,7 +702,7 @@ pub fn slide_calculate(key: i32, gt: &str, challenge: &str, c: &[u8], s: &str) -
         "lang": "zh-cn",
         "userresponse": user_response,
         "passtime": pass_time,
-        "imgload": thread_rng().gen_range(100..=200),
+        "imgload": rng.gen_range(100..=200),
         "aa": aa,
         "ep": {
             "v": "9.1.8-bfget5",
@@ -493,5 +749,330 @@ pub fn slide_calculate(key: i32, gt: &str, challenge: &str, c: &[u8], s: &str) -
         },
         "rp": rp,
     });
-    encrypt(dic.to_string().as_str())
-}
\ No newline at end of file
+    Ok(encrypt(dic.to_string().as_str(), rng, trace))
+}
+/// ### 根据关键参数生成w
+/// - 纯本地计算，不依赖网络和客户端；Click/Slide 的 generate_w 也走这里
+/// - 点选的 key 为坐标串，不使用 c、s 和轨迹参数；滑块的 key 为滑动距离
+pub fn generate_w(
+    kind: VerifyType,
+    key: &str,
+    gt: &str,
+    challenge: &str,
+    c: &[u8],
+    s: &str,
+    options: &WOptions,
+) -> Result<String> {
+    let algorithm = w_algorithm(options.algo_version)?;
+    let mut rng = options.rng();
+    match kind {
+        VerifyType::Click => Ok((algorithm.click)(key, gt, challenge, &mut rng, &mut None)),
+        VerifyType::Slide => {
+            (algorithm.slide)(slide_distance(key)?, gt, challenge, c, s, &options.track, &mut rng, &mut None)
+        }
+        VerifyType::Nine | VerifyType::Beeline => Err(kind.unsupported()),
+    }
+}
+
+/// 与 generate_w 相同，同时返回中间值
+pub fn generate_w_debug(
+    kind: VerifyType,
+    key: &str,
+    gt: &str,
+    challenge: &str,
+    c: &[u8],
+    s: &str,
+    options: &WOptions,
+) -> Result<(String, WDebug)> {
+    let algorithm = w_algorithm(options.algo_version)?;
+    let mut rng = options.rng();
+    let (w, trace) = match kind {
+        VerifyType::Click => {
+            let mut trace = Some(WDebug { key_len: key.len(), ..Default::default() });
+            ((algorithm.click)(key, gt, challenge, &mut rng, &mut trace), trace)
+        }
+        VerifyType::Slide => {
+            let distance = slide_distance(key)?;
+            let mut trace = Some(WDebug { key_len: distance.to_string().len(), ..Default::default() });
+            ((algorithm.slide)(distance, gt, challenge, c, s, &options.track, &mut rng, &mut trace)?, trace)
+        }
+        VerifyType::Nine | VerifyType::Beeline => return Err(kind.unsupported()),
+    };
+    Ok((w, trace.unwrap_or_default()))
+}
+
+/// RSA 加密的会话密钥部分的长度（1024 位密文的十六进制）
+const RSA_HEX_LEN: usize = 256;
+
+/// ### w 的解析结果
+/// - 用于排查，只反映能从 w 中读出的信息
+/// - 载荷用本服务固定的会话密钥加密，浏览器生成的 w 使用随机会话密钥，只能检查格式
+#[derive(Debug, Default, Serialize, JsonSchema)]
+pub struct WDecoded {
+    pub length: usize,
+    /// RSA 部分是否为 256 个十六进制字符
+    pub rsa_valid: bool,
+    /// 载荷能否用本服务的会话密钥解密并解析为 JSON
+    pub decrypted: bool,
+    /// 由字段推断的类型: click / slide
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<&'static str>,
+    /// 载荷中的字段名
+    pub fields: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pass_time: Option<i64>,
+    /// 点选坐标串；滑块在给出 challenge 时还原出的滑动距离
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// 给出 gt、challenge 时 rp 是否与之相符
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rp_matches: Option<bool>,
+    /// 给出 c、s 时轨迹中按 c、s 插入的字符是否都在预期位置；c、s 本身无法从 w 还原
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub c_s_matches: Option<bool>,
+    /// 无法解析的部分及原因
+    pub errors: Vec<String>,
+}
+
+/// ### 解析 w，检查格式并尽量还原其中的参数
+/// - w 由载荷（AES 密文的 base64）与末尾 256 个字符的 RSA 密文组成
+/// - gt、challenge、c、s 都可省略，省略时跳过对应的核对
+pub fn decode_w(
+    w: &str,
+    gt: Option<&str>,
+    challenge: Option<&str>,
+    c: Option<&[u8]>,
+    s: Option<&str>,
+) -> WDecoded {
+    let mut decoded = WDecoded { length: w.len(), ..Default::default() };
+    if !w.is_ascii() || w.len() <= RSA_HEX_LEN {
+        decoded.errors.push(format!("长度应大于 {} 且只含 ASCII 字符", RSA_HEX_LEN));
+        return decoded;
+    }
+    let (payload, rsa) = w.split_at(w.len() - RSA_HEX_LEN);
+    decoded.rsa_valid = rsa.bytes().all(|b| b.is_ascii_hexdigit());
+    if !decoded.rsa_valid {
+        decoded.errors.push("RSA 部分不是十六进制".to_string());
+    }
+    let Some(cipher) = base64_decode(payload) else {
+        decoded.errors.push("载荷不是有效的 base64".to_string());
+        return decoded;
+    };
+    let Some(dic) = aes_dec_cbc(&cipher, AES_KEY.as_bytes(), &AES_IV, Some("PKCS7"))
+        .ok()
+        .and_then(|plain| serde_json::from_slice::<serde_json::Value>(&plain).ok())
+        .filter(serde_json::Value::is_object)
+    else {
+        decoded.errors.push("载荷无法用本服务的会话密钥解密，可能不是本服务生成的".to_string());
+        return decoded;
+    };
+    decoded.decrypted = true;
+    decoded.fields = dic.as_object().map(|map| map.keys().cloned().collect()).unwrap_or_default();
+    decoded.pass_time = dic.get("passtime").and_then(serde_json::Value::as_i64);
+
+    if let Some(points) = dic.get("a").and_then(serde_json::Value::as_str) {
+        decoded.kind = Some(VerifyType::Click.as_str());
+        decoded.key = Some(points.to_string());
+    } else if let Some(response) = dic.get("userresponse").and_then(serde_json::Value::as_str) {
+        decoded.kind = Some(VerifyType::Slide.as_str());
+        match challenge.map(|challenge| user_response_distance(response, challenge)) {
+            Some(Some(distance)) => decoded.key = Some(distance.to_string()),
+            Some(None) => decoded.errors.push("userresponse 与 challenge 不符".to_string()),
+            None => {}
+        }
+    } else {
+        decoded.errors.push("缺少点选的 a 或滑块的 userresponse".to_string());
+    }
+
+    if let (Some(gt), Some(challenge), Some(pass_time)) = (gt, challenge, decoded.pass_time) {
+        let prefix = challenge.get(..challenge.len().saturating_sub(2)).unwrap_or_default();
+        let expected = hex::encode(md5::compute(format!("{}{}{}", gt, prefix, pass_time)).to_vec());
+        decoded.rp_matches = Some(dic.get("rp").and_then(serde_json::Value::as_str) == Some(expected.as_str()));
+    }
+    if let (Some(c), Some(s), Some(aa)) = (c, s, dic.get("aa").and_then(serde_json::Value::as_str)) {
+        decoded.c_s_matches = c_s_inserted(aa, c, s);
+    }
+    decoded
+}
+
+/// ### user_response 的逆运算，还原滑动距离
+/// - challenge 不足两个字符、response 中出现不代表任何权重的字符时返回 None
+fn user_response_distance(response: &str, challenge: &str) -> Option<i32> {
+    let chars: Vec<char> = challenge.chars().collect();
+    if chars.len() < 2 {
+        return None;
+    }
+    let digit = |c: char| if c as i32 > 57 { c as i32 - 87 } else { c as i32 - 48 };
+    let n = 36 * digit(chars[chars.len() - 2]) + digit(chars[chars.len() - 1]);
+    // 与 user_response 相同的分组，每组第一个字符代表对应的权重
+    let mut firsts: Vec<Option<char>> = vec![None; 5];
+    let mut seen = HashSet::new();
+    let mut idx = 0;
+    for c in &chars[..chars.len() - 2] {
+        if seen.insert(*c) {
+            firsts[idx].get_or_insert(*c);
+            idx = (idx + 1) % 5;
+        }
+    }
+    let weights = [1, 2, 5, 10, 50];
+    let sum = response
+        .chars()
+        .map(|c| firsts.iter().position(|first| *first == Some(c)).map(|d| weights[d]))
+        .sum::<Option<i32>>()?;
+    Some(sum - n)
+}
+
+/// ### 核对轨迹密文中按 c、s 插入的字符
+/// - 按 final_encrypt 的插入顺序倒序取出，每个位置都是预期字符时返回 true
+/// - c 不足 5 个或 s 为空时不插入字符，返回 None
+fn c_s_inserted(aa: &str, c: &[u8], s: &str) -> Option<bool> {
+    if c.len() < 5 || s.is_empty() {
+        return None;
+    }
+    let Some(inserted) = s
+        .as_bytes()
+        .chunks_exact(2)
+        .map(|pair| std::str::from_utf8(pair).ok().and_then(|pair| u8::from_str_radix(pair, 16).ok()))
+        .collect::<Option<Vec<u8>>>()
+    else {
+        return Some(false);
+    };
+    let inserted_len: usize = inserted.iter().map(|&b| char::from(b).len_utf8()).sum();
+    let Some(original_len) = aa.len().checked_sub(inserted_len).filter(|&len| len > 0) else {
+        return Some(false);
+    };
+    let (s0, a, m) = (c[0] as u64, c[2] as u64, c[4] as u64);
+    let mut o = aa.to_string();
+    for &b in inserted.iter().rev() {
+        let x = b as u64;
+        let ll = next_char_boundary(&o, ((s0 * x * x + a * x + m) % original_len as u64) as usize);
+        if o.get(ll..).and_then(|rest| rest.chars().next()) != Some(char::from(b)) {
+            return Some(false);
+        }
+        o.remove(ll);
+    }
+    Some(true)
+}
+
+/// 解析滑块的 key，不是整数或超出范围时返回 InvalidInput
+fn slide_distance(key: &str) -> Result<i32> {
+    match key.trim().parse::<i32>() {
+        Ok(distance) => check_slide_distance(distance),
+        Err(_) => Err(invalid_input(&format!("滑动距离不是整数: {}", key))),
+    }
+}
+
+/// 滑块背景图宽度，滑动距离不会超过它
+pub const MAX_SLIDE_DISTANCE: i32 = 260;
+
+/// 滑动距离须在 0..=MAX_SLIDE_DISTANCE 之间，否则返回 InvalidInput
+fn check_slide_distance(distance: i32) -> Result<i32> {
+    if !(0..=MAX_SLIDE_DISTANCE).contains(&distance) {
+        return Err(invalid_input(&format!("滑动距离超出范围 0..={}: {}", MAX_SLIDE_DISTANCE, distance)));
+    }
+    Ok(distance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn algo_version_defaults_to_latest_and_rejects_unknown() {
+        assert_eq!(w_algorithm_versions().last(), Some(&W_ALGORITHM_VERSION));
+        assert_eq!(w_algorithm(None).unwrap().version, W_ALGORITHM_VERSION);
+        let options = WOptions { algo_version: Some(0), ..Default::default() };
+        let err = generate_w(VerifyType::Click, "1_2", "gt", "challenge1a", &[], "", &options).unwrap_err();
+        assert_eq!(err.code(), crate::error::ErrorCode::InvalidInput);
+    }
+
+    #[test]
+    fn base64_decode_reverses_base64() {
+        for len in 0..8 {
+            let input = (0..len).map(|i| (i * 37 + 11) as u8).collect::<Vec<_>>();
+            assert_eq!(base64_decode(&base64(&input)).unwrap(), input);
+        }
+        assert!(base64_decode("A+").is_none());
+        assert!(base64_decode("ABCDE").is_none());
+    }
+
+    #[test]
+    fn decode_w_recovers_what_generate_w_encoded() {
+        let options = WOptions { seed: Some(3), ..Default::default() };
+        let (gt, challenge) = ("gt", "challenge1a");
+        let w = generate_w(VerifyType::Slide, "120", gt, challenge, &[1, 2, 3, 4, 5], "6a7b", &options).unwrap();
+        let decoded = decode_w(&w, Some(gt), Some(challenge), Some(&[1, 2, 3, 4, 5]), Some("6a7b"));
+        assert!(decoded.rsa_valid && decoded.decrypted, "{:?}", decoded.errors);
+        assert_eq!(decoded.kind, Some("slide"));
+        assert_eq!(decoded.key.as_deref(), Some("120"));
+        assert_eq!(decoded.rp_matches, Some(true));
+        assert_eq!(decoded.c_s_matches, Some(true));
+        assert!(decoded.fields.iter().any(|field| field == "aa"));
+
+        // 换一组 c、s 或 gt 时核对不通过
+        let decoded = decode_w(&w, Some("other"), Some(challenge), Some(&[9, 8, 7, 6, 5]), Some("6a7b"));
+        assert_eq!(decoded.rp_matches, Some(false));
+        assert_eq!(decoded.c_s_matches, Some(false));
+
+        let w = generate_w(VerifyType::Click, "1_2,3_4", gt, challenge, &[], "", &options).unwrap();
+        let decoded = decode_w(&w, None, None, None, None);
+        assert_eq!(decoded.kind, Some("click"));
+        assert_eq!(decoded.key.as_deref(), Some("1_2,3_4"));
+        assert!(decoded.rp_matches.is_none());
+    }
+
+    #[test]
+    fn slide_rejects_invalid_input_without_panicking() {
+        let options = WOptions { seed: Some(3), ..Default::default() };
+        let c = [1, 2, 3, 4, 5];
+        let slide = |key: &str, challenge: &str, s: &str| generate_w(VerifyType::Slide, key, "gt", challenge, &c, s, &options);
+        let invalid = |res: Result<String>| res.unwrap_err().code() == crate::error::ErrorCode::InvalidInput;
+
+        // 滑动距离限定在 0..=260
+        assert!(slide("260", "challenge1a", "6a7b").is_ok());
+        assert!(invalid(slide("261", "challenge1a", "6a7b")));
+        assert!(invalid(slide("2147483647", "challenge1a", "6a7b")));
+        // s 须为非空、偶数长度的十六进制串
+        for s in ["", "6", "6a7", "zz", "é1", "6é"] {
+            assert!(invalid(slide("120", "challenge1a", s)), "{:?}", s);
+        }
+        // challenge 太短、不是 ASCII 或不同字符不足时无法生成 userresponse
+        for challenge in ["a", "é1a", "aaaaaaaaaa1a"] {
+            assert!(invalid(slide("120", challenge, "6a7b")), "{:?}", challenge);
+        }
+    }
+
+    #[test]
+    fn track_options_are_bounded() {
+        assert!(TrackOptions { points: Some(MAX_TRACK_POINTS), ..Default::default() }.validate().is_ok());
+        let too_many = TrackOptions { points: Some(u32::MAX), ..Default::default() };
+        assert_eq!(too_many.validate().unwrap_err().code(), crate::error::ErrorCode::InvalidInput);
+        let too_long = TrackOptions { duration_ms: Some([100, u32::MAX]), ..Default::default() };
+        assert_eq!(too_long.validate().unwrap_err().code(), crate::error::ErrorCode::InvalidInput);
+
+        // 直接调用库时超出上限的值被截断，u32::MAX 不会转换成负的点数
+        let mut rng = StdRng::seed_from_u64(1);
+        let track = get_slide_track(100, &too_many, &mut rng).unwrap();
+        assert!(track.len() <= MAX_TRACK_POINTS as usize + 3);
+        assert!(get_slide_track(100, &too_long, &mut rng).is_ok());
+    }
+
+    #[test]
+    fn high_bytes_in_s_are_inserted_on_char_boundaries() {
+        let options = WOptions { seed: Some(3), ..Default::default() };
+        let c = [1, 2, 3, 4, 5];
+        let w = generate_w(VerifyType::Slide, "120", "gt", "challenge1a", &c, "ff80c3a9", &options).unwrap();
+        let decoded = decode_w(&w, Some("gt"), Some("challenge1a"), Some(&c), Some("ff80c3a9"));
+        assert_eq!(decoded.c_s_matches, Some(true));
+    }
+
+    #[test]
+    fn decode_w_reports_malformed_input() {
+        assert!(!decode_w("short", None, None, None, None).errors.is_empty());
+        let garbage = format!("{}{}", "ABCD".repeat(8), "0".repeat(RSA_HEX_LEN));
+        let decoded = decode_w(&garbage, None, None, None, None);
+        assert!(decoded.rsa_valid);
+        assert!(!decoded.decrypted);
+        assert_eq!(decoded.errors.len(), 1);
+    }
+}
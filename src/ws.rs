@@ -0,0 +1,245 @@
+// ws.rs
+
+use crate::abstraction::{Api, GenerateW, VerifyType};
+use crate::error::{self, ErrorCode, Result};
+use crate::{click_instance, record_breaker, slide_instance, validate_input, AppState, ClientParams};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::response::Response;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tokio::task;
+
+const ROUTE: &str = "/ws/solve";
+/// 点选从计算 key 到提交验证至少间隔的时间，与 Click::simple_match 一致
+const CLICK_MIN_SOLVE_TIME: Duration = Duration::from_secs(2);
+
+/// ### 客户端发送的求解参数
+/// - 连接建立后发送的第一条文本消息
+#[derive(Deserialize)]
+struct SolveFrame {
+    kind: VerifyType,
+    gt: String,
+    challenge: String,
+    session_id: Option<String>,
+    #[serde(flatten)]
+    client: ClientParams,
+}
+
+/// ### 推送给客户端的进度事件
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum SolveEvent {
+    Registered {
+        c: Vec<u8>,
+        s: String,
+    },
+    TypeDetected {
+        #[serde(rename = "type")]
+        verify_type: &'static str,
+    },
+    WGenerated {
+        w: String,
+    },
+    Verified {
+        message: String,
+        validate: String,
+    },
+    Error {
+        code: ErrorCode,
+        message: String,
+    },
+}
+
+/// 单个步骤的结果
+enum Step<R> {
+    Done(R),
+    Failed(error::Error),
+    /// 客户端已断开
+    Cancelled,
+}
+
+macro_rules! try_step {
+    ($step:expr) => {
+        match $step {
+            Step::Done(v) => v,
+            Step::Failed(e) => return Step::Failed(e),
+            Step::Cancelled => return Step::Cancelled,
+        }
+    };
+}
+
+/// ### WebSocket 求解
+/// - 每完成一步推送一条事件: registered / type_detected / w_generated / verified
+/// - 客户端中途关闭连接时不再执行后续步骤
+pub(crate) async fn ws_solve(State(state): State<AppState>, ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(move |socket| solve(socket, state))
+}
+
+async fn solve(mut socket: WebSocket, state: AppState) {
+    let frame = loop {
+        match socket.recv().await {
+            Some(Ok(Message::Text(text))) => break text,
+            Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+            Some(Ok(_)) => continue,
+        }
+    };
+    state.metrics.record_request(ROUTE);
+    let started = Instant::now();
+
+    let outcome = match serde_json::from_str::<SolveFrame>(&frame) {
+        Err(e) => Err((ErrorCode::InvalidInput, format!("无效的求解参数: {}", e))),
+        Ok(frame) => dispatch(&mut socket, &state, frame).await,
+    };
+
+    match outcome {
+        Ok(()) => state.metrics.record_outcome(ROUTE, None),
+        Err((code, message)) => {
+            tracing::error!(error_code = code.as_str(), "WebSocket 求解失败: {}", message);
+            state.metrics.record_outcome(ROUTE, Some(code));
+            let _ = send(&mut socket, &SolveEvent::Error { code, message }).await;
+        }
+    }
+    state.metrics.observe_latency(ROUTE, started.elapsed());
+    let _ = socket.send(Message::Close(None)).await;
+}
+
+/// ### 按验证类型取出实例并执行求解
+/// - 与 HTTP 求解一样先按会话限流，再占用一个求解名额直到整个求解结束
+async fn dispatch(
+    socket: &mut WebSocket,
+    state: &AppState,
+    frame: SolveFrame,
+) -> std::result::Result<(), (ErrorCode, String)> {
+    validate_input(&frame.gt, &frame.challenge).map_err(|e| (e.code, e.message))?;
+    state.check_rate_limit(frame.session_id.as_deref()).map_err(|e| (e.code, e.message))?;
+    let _permit = state.acquire_solve_permit().await.map_err(|e| (e.code, e.message))?;
+    let step = match frame.kind {
+        VerifyType::Click => {
+            let instance = click_instance(state, frame.session_id, &frame.client)
+                .await
+                .map_err(|e| (e.code, e.message))?;
+            run(socket, instance, frame.gt, frame.challenge, |_| None, CLICK_MIN_SOLVE_TIME).await
+        }
+        VerifyType::Slide => {
+            let instance = slide_instance(state, frame.session_id, &frame.client)
+                .await
+                .map_err(|e| (e.code, e.message))?;
+            // 滑块刷新后使用新的 challenge 生成 w 与验证
+            run(socket, instance, frame.gt, frame.challenge, |args| Some(args.0.clone()), Duration::ZERO).await
+        }
+        kind @ (VerifyType::Nine | VerifyType::Beeline) => {
+            let e = kind.unsupported();
+            return Err((e.code(), e.to_string()));
+        }
+    };
+    match step {
+        Step::Done(()) => Ok(()),
+        Step::Failed(e) => Err((e.code(), e.to_string())),
+        Step::Cancelled => {
+            tracing::info!("客户端已断开，取消求解");
+            Err((ErrorCode::Other, "客户端已断开".to_string()))
+        }
+    }
+}
+
+async fn send(socket: &mut WebSocket, event: &SolveEvent) -> bool {
+    match serde_json::to_string(event) {
+        Ok(text) => socket.send(Message::Text(text)).await.is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// ### 在阻塞线程池中执行一步
+/// - 等待期间同时监听连接，客户端关闭时立即返回 Cancelled
+/// - 已开始的阻塞请求无法中断，但其结果会被丢弃，也不更新熔断
+/// - 每步的结果与 handle_blocking_call! 一样计入实例当前代理的熔断
+async fn step<T, R, F>(socket: &mut WebSocket, mut instance: T, f: F) -> Step<(T, R)>
+where
+    T: Api + Send + 'static,
+    R: Send + 'static,
+    F: FnOnce(&mut T) -> Result<R> + Send + 'static,
+{
+    let mut handle = task::spawn_blocking(move || {
+        let res = f(&mut instance);
+        (instance, res)
+    });
+    loop {
+        tokio::select! {
+            joined = &mut handle => {
+                return match joined {
+                    Ok((instance, res)) => {
+                        record_breaker(&instance, &res);
+                        match res {
+                            Ok(r) => Step::Done((instance, r)),
+                            Err(e) => Step::Failed(e),
+                        }
+                    }
+                    Err(e) => Step::Failed(error::other("任务执行失败", e)),
+                };
+            }
+            msg = socket.recv() => match msg {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return Step::Cancelled,
+                Some(Ok(_)) => continue,
+            },
+        }
+    }
+}
+
+/// ### 依次执行求解步骤并推送事件
+/// - refreshed: 从刷新参数中取出新的 challenge，点选不需要
+/// - min_solve_time: 从计算 key 到提交验证的最短间隔
+async fn run<T>(
+    socket: &mut WebSocket,
+    instance: T,
+    gt: String,
+    challenge: String,
+    refreshed: fn(&T::ArgsType) -> Option<String>,
+    min_solve_time: Duration,
+) -> Step<()>
+where
+    T: GenerateW + Send + 'static,
+    T::ArgsType: Send,
+{
+    macro_rules! emit {
+        ($event:expr) => {
+            if !send(socket, &$event).await {
+                return Step::Cancelled;
+            }
+        };
+    }
+
+    let (g, ch) = (gt.clone(), challenge.clone());
+    let (instance, (c, s)) = try_step!(step(socket, instance, move |i: &mut T| i.get_c_s(&g, &ch, None)).await);
+    emit!(SolveEvent::Registered { c: c.clone(), s: s.clone() });
+
+    let (g, ch) = (gt.clone(), challenge.clone());
+    let (instance, verify_type) = try_step!(step(socket, instance, move |i: &mut T| i.get_type(&g, &ch, None)).await);
+    emit!(SolveEvent::TypeDetected { verify_type: verify_type.as_str() });
+
+    let g = gt.clone();
+    let (instance, (w, challenge, solve_started)) = try_step!(
+        step(socket, instance, move |i: &mut T| {
+            let (_, _, args) = i.get_new_c_s_args(&g, &challenge)?;
+            let challenge = refreshed(&args).unwrap_or(challenge);
+            let solve_started = Instant::now();
+            let key = i.calculate_key(args)?;
+            let w = i.generate_w(&key, &g, &challenge, &c, &s)?;
+            Ok((w, challenge, solve_started))
+        })
+        .await
+    );
+    emit!(SolveEvent::WGenerated { w: w.clone() });
+
+    let (_, (message, validate)) = try_step!(
+        step(socket, instance, move |i: &mut T| {
+            if let Some(remaining) = min_solve_time.checked_sub(solve_started.elapsed()) {
+                std::thread::sleep(remaining);
+            }
+            i.verify(&gt, &challenge, Some(&w))
+        })
+        .await
+    );
+    emit!(SolveEvent::Verified { message, validate });
+    Step::Done(())
+}
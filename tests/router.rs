@@ -0,0 +1,324 @@
+// router.rs
+
+//! ### 路由集成测试
+//! - 启动编译好的服务进程，通过 GEETEST_BASE_URL 指向本机的极验桩，端到端检查各路由的状态码与 ApiResponse 结构
+//! - 极验桩按 challenge 的首字符决定行为，见 `geetest_stub`
+//! - 只覆盖滑块与不访问极验的路由，点选实例会加载识别模型
+
+use axum::extract::Query;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::TcpListener;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+const GT: &str = "0123456789abcdef0123456789abcdef";
+/// 极验正常响应
+const CHALLENGE_OK: &str = "fedcba9876543210fedcba9876543210";
+/// 极验返回 500
+const CHALLENGE_5XX: &str = "5edcba9876543210fedcba9876543210";
+/// 极验返回 403，出口 IP 被封禁
+const CHALLENGE_BANNED: &str = "dedcba9876543210fedcba9876543210";
+/// 极验验证未通过
+const CHALLENGE_REJECTED: &str = "bedcba9876543210fedcba9876543210";
+/// 本机请求的耗时上限，超出说明服务在等待不存在的上游或被阻塞
+const MAX_LATENCY: Duration = Duration::from_secs(5);
+/// 这些路由的响应都很小，超出说明带上了不该返回的内容
+const MAX_BODY: usize = 16 * 1024;
+
+/// 按 jsonp 回调包裹响应
+fn jsonp(params: &HashMap<String, String>, body: Value) -> String {
+    format!(
+        "{}({})",
+        params
+            .get("callback")
+            .map(String::as_str)
+            .unwrap_or("callback"),
+        body
+    )
+}
+
+/// ### 极验桩
+/// - challenge 以 5 开头时返回 500
+/// - challenge 以 d 开头时获取 c/s 返回 403
+/// - challenge 以 b 开头时验证返回 fail，不带 validate
+/// - 其余请求按滑块验证码的正常流程响应
+fn geetest_stub() -> Router {
+    async fn get_php(Query(params): Query<HashMap<String, String>>) -> impl IntoResponse {
+        if params["challenge"].starts_with('5') {
+            return (StatusCode::INTERNAL_SERVER_ERROR, "boom".to_string());
+        }
+        if params["challenge"].starts_with('d') {
+            return (StatusCode::FORBIDDEN, "forbidden".to_string());
+        }
+        let body = json!({
+            "status": "success",
+            "data": { "c": [12, 58, 98, 36, 43, 95, 62, 15, 12], "s": "3f2e1d0c" },
+        });
+        (StatusCode::OK, jsonp(&params, body))
+    }
+    async fn ajax_php(Query(params): Query<HashMap<String, String>>) -> impl IntoResponse {
+        let challenge = params["challenge"].as_str();
+        if challenge.starts_with('5') {
+            return (StatusCode::INTERNAL_SERVER_ERROR, "boom".to_string());
+        }
+        // 带 w 的是验证请求，否则是获取类型
+        let body = match params.get("w") {
+            None => json!({ "status": "success", "data": { "result": "slide" } }),
+            Some(_) if challenge.starts_with('b') => json!({ "success": 0, "message": "fail" }),
+            Some(_) => {
+                json!({ "success": 1, "message": "success", "validate": "0123456789abcdef", "score": "1" })
+            }
+        };
+        (StatusCode::OK, jsonp(&params, body))
+    }
+    Router::new()
+        .route("/get.php", get(get_php))
+        .route("/ajax.php", get(ajax_php))
+}
+
+/// 服务进程，drop 时结束
+struct Server {
+    child: Child,
+    base: String,
+    http: reqwest::Client,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// 空闲的本机端口
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("获取空闲端口失败")
+        .port()
+}
+
+impl Server {
+    /// 启动极验桩和服务进程，等待服务可以响应
+    async fn start() -> Self {
+        let stub = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("绑定极验桩端口失败");
+        let stub_url = format!("http://{}", stub.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(stub, geetest_stub()).await });
+
+        let bind = format!("127.0.0.1:{}", free_port());
+        let mut command = Command::new(env!("CARGO_BIN_EXE_bili_ticket_gt_server"));
+        command
+            .env("BILITICKER_GT_BIND", &bind)
+            .env("GEETEST_BASE_URL", &stub_url)
+            .env("NO_PROXY", "127.0.0.1")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        // 测试环境的系统代理不能转发本机请求
+        for name in [
+            "HTTP_PROXY",
+            "http_proxy",
+            "HTTPS_PROXY",
+            "https_proxy",
+            "ALL_PROXY",
+            "all_proxy",
+        ] {
+            command.env_remove(name);
+        }
+        let server = Server {
+            child: command.spawn().expect("启动服务失败"),
+            base: format!("http://{}", bind),
+            http: reqwest::Client::builder().no_proxy().build().unwrap(),
+        };
+
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while Instant::now() < deadline {
+            if server
+                .http
+                .get(server.url("/sessions/count"))
+                .send()
+                .await
+                .is_ok()
+            {
+                return server;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("服务未在 30 秒内启动");
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base, path)
+    }
+
+    /// ### 发送请求并检查响应
+    /// - 本机请求应在 MAX_LATENCY 内完成
+    /// - 响应带 Content-Length 时与响应体长度一致，且响应体不超过 MAX_BODY
+    /// - 响应体是字段齐全的 ApiResponse
+    async fn call(&self, request: reqwest::RequestBuilder) -> (StatusCode, Value) {
+        let started = Instant::now();
+        let res = request.send().await.expect("请求服务失败");
+        let status = StatusCode::from_u16(res.status().as_u16()).unwrap();
+        let declared = res.content_length();
+        let body = res.bytes().await.expect("读取响应体失败");
+        assert!(
+            started.elapsed() < MAX_LATENCY,
+            "响应耗时 {:?}",
+            started.elapsed()
+        );
+        if let Some(declared) = declared {
+            assert_eq!(declared, body.len() as u64);
+        }
+        assert!(body.len() <= MAX_BODY, "响应体 {} 字节", body.len());
+
+        let body: Value = serde_json::from_slice(&body).expect("响应体不是 JSON");
+        let mut fields: Vec<&str> = body
+            .as_object()
+            .expect("响应体不是对象")
+            .keys()
+            .map(String::as_str)
+            .collect();
+        fields.sort_unstable();
+        assert_eq!(fields, ["code", "data", "error", "error_code", "success"]);
+        assert_eq!(body["success"], status.is_success());
+        (status, body)
+    }
+
+    async fn get(&self, path: &str) -> (StatusCode, Value) {
+        self.call(self.http.get(self.url(path))).await
+    }
+
+    async fn post(&self, path: &str, body: Value) -> (StatusCode, Value) {
+        self.call(self.http.post(self.url(path)).json(&body)).await
+    }
+}
+
+fn gt_challenge(challenge: &str) -> Value {
+    json!({ "gt": GT, "challenge": challenge })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn slide_routes_succeed_against_stub() {
+    let server = Server::start().await;
+
+    let (status, body) = server
+        .post("/slide/get_c_s", gt_challenge(CHALLENGE_OK))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body["data"]["c"],
+        json!([12, 58, 98, 36, 43, 95, 62, 15, 12])
+    );
+    assert_eq!(body["data"]["s"], "3f2e1d0c");
+    assert!(body["data"].get("timings").is_none());
+
+    let mut get_c_s = gt_challenge(CHALLENGE_OK);
+    get_c_s["include_timings"] = true.into();
+    let (status, body) = server.post("/slide/get_c_s", get_c_s).await;
+    assert_eq!(status, StatusCode::OK);
+    let timings = &body["data"]["timings"];
+    let total = timings["total_ms"].as_u64().unwrap();
+    assert!(timings["network_ms"].as_u64().unwrap() <= total);
+    assert!(timings["compute_ms"].as_u64().unwrap() <= total);
+
+    let (status, body) = server
+        .post("/slide/get_type", gt_challenge(CHALLENGE_OK))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"], "slide");
+
+    let mut verify = gt_challenge(CHALLENGE_OK);
+    verify["w"] = "w".into();
+    let (status, body) = server.post("/slide/verify", verify).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["first"], "success");
+    assert_eq!(body["data"]["second"], "0123456789abcdef");
+    assert!(body["data"].get("timings").is_none());
+
+    // 只能发 GET 的调用方用查询参数提交，结果与 POST 相同
+    let (status, body) = server
+        .get(&format!("/slide/verify?gt={}&challenge={}&w=w", GT, CHALLENGE_OK))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["first"], "success");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upstream_failures_map_to_error_envelopes() {
+    let server = Server::start().await;
+
+    // 极验返回 500 时报上游错误，带上响应体
+    let (status, body) = server
+        .post("/slide/get_c_s", gt_challenge(CHALLENGE_5XX))
+        .await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert_eq!(body["error_code"], "upstream_status");
+    assert!(body["error"].as_str().unwrap().contains("boom"));
+
+    let (status, body) = server
+        .post("/slide/get_type", gt_challenge(CHALLENGE_5XX))
+        .await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert_eq!(body["error_code"], "upstream_status");
+
+    // 验证未通过是极验的业务拒绝，不是上游故障
+    let mut verify = gt_challenge(CHALLENGE_REJECTED);
+    verify["w"] = "w".into();
+    let (status, body) = server.post("/slide/verify", verify).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error_code"], "rejected");
+
+    // 出口 IP 被封禁单独报错，便于调用方更换代理
+    let (status, body) = server
+        .post("/slide/get_c_s", gt_challenge(CHALLENGE_BANNED))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error_code"], "ip_banned");
+
+    // 参数格式不对时不会请求极验
+    let (status, body) = server
+        .post("/slide/get_c_s", gt_challenge("not-a-challenge"))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error_code"], "invalid_input");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn local_routes_use_the_envelope() {
+    let server = Server::start().await;
+
+    let (status, _) = server.get("/sessions/count").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = server
+        .post(
+            "/generate_w",
+            json!({
+                "kind": "slide",
+                "key": "120",
+                "gt": GT,
+                "challenge": CHALLENGE_OK,
+                "c": [12, 58, 98, 36, 43, 95, 62, 15, 12],
+                "s": "3f2e1d0c",
+            }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["data"].is_string());
+
+    // 存活检查返回纯文本，不走 ApiResponse
+    let res = server.http.get(server.url("/health")).send().await.unwrap();
+    assert_eq!(res.status().as_u16(), StatusCode::OK.as_u16());
+    assert_eq!(res.text().await.unwrap(), "OK");
+
+    // 未知路由同样返回 ApiResponse
+    let (status, body) = server.get("/no/such/route").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["error_code"], "other");
+}